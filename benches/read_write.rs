@@ -0,0 +1,53 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use iptc::{IPTC, IPTCTag};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+/// An APP13 segment close to the 64 KiB limit: a long caption and
+/// hundreds of keywords.
+fn large_jpeg() -> Vec<u8> {
+    let caption = "Lorem ipsum dolor sit amet. ".repeat(70);
+    let keywords: Vec<String> = (0..2000).map(|i| format!("keyword {i}")).collect();
+
+    let mut datasets: Vec<(u8, u8, &[u8])> = vec![(2, 0, &[0, 4]), (2, 120, caption.as_bytes())];
+    datasets.extend(keywords.iter().map(|keyword| (2, 25, keyword.as_bytes())));
+    datasets.extend([(2, 80, &b"Jane Doe"[..]), (2, 90, b"Oslo")]);
+    common::jpeg_with(&datasets, &[], None)
+}
+
+fn read(c: &mut Criterion) {
+    let small = std::fs::read("tests/smiley.jpg").unwrap();
+    let large = large_jpeg();
+    let tiff = std::fs::read("tests/DSC3003.tif").unwrap();
+
+    c.bench_function("read jpeg small", |b| {
+        b.iter(|| IPTC::read_from_buffer(black_box(&small)).unwrap())
+    });
+    c.bench_function("read jpeg large", |b| {
+        b.iter(|| IPTC::read_from_buffer(black_box(&large)).unwrap())
+    });
+    c.bench_function("read tiff", |b| {
+        b.iter(|| IPTC::read_from_buffer(black_box(&tiff)).unwrap())
+    });
+}
+
+fn write(c: &mut Criterion) {
+    for (name, image) in [
+        (
+            "write jpeg small",
+            std::fs::read("tests/smiley.jpg").unwrap(),
+        ),
+        ("write jpeg large", large_jpeg()),
+    ] {
+        let mut iptc = IPTC::read_from_buffer(&image).unwrap();
+        iptc.set_tag(IPTCTag::Headline, "A new headline");
+
+        c.bench_function(name, |b| {
+            b.iter(|| iptc.write_to_buffer(black_box(&image)).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, read, write);
+criterion_main!(benches);
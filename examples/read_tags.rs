@@ -6,7 +6,7 @@ use std::path::Path;
 fn main() -> Result<(), Box<dyn Error>> {
     let image_path = Path::new("tests/smiley.jpg");
 
-    let iptc = IPTC::read_from_path(&image_path)?;
+    let iptc = IPTC::read_from_path(image_path)?;
 
     println!("IPTC: {:?}", iptc.get_all());
 
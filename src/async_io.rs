@@ -0,0 +1,123 @@
+use crate::IPTC;
+use crate::WriteOptions;
+use crate::jpeg::{APP13, JPEGReader, PHOTOSHOP_SIGNATURE, SegmentRef, SegmentWalker, Step};
+use crate::reader::{IIMData, read_iptc_data};
+use crate::validate;
+use std::error::Error;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+impl IPTC {
+    /// Reads IPTC metadata from an async stream holding a JPEG image.
+    ///
+    /// JPEG segments are read one at a time, up to the Photoshop one, and the
+    /// image data is never read. TIFF directories can be anywhere in the
+    /// file, which a stream that can't seek can't reach without reading it
+    /// whole, so TIFF images are an error here; use `read_from_reader`.
+    pub async fn read_from_async_reader<R: AsyncRead + Unpin>(
+        reader: &mut R,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut walker = SegmentWalker::new(Some(APP13));
+        let found = walk_segments(reader, &mut walker, |segment| {
+            segment
+                .payload
+                .starts_with(PHOTOSHOP_SIGNATURE)
+                .then(|| read_segment(segment))
+        })
+        .await?;
+
+        let mut iptc = IPTC::new();
+        if let Some(result) = found {
+            (iptc.data, iptc.datasets) = result?;
+        }
+        Ok(iptc)
+    }
+
+    /// Copies a JPEG image from `reader` to `writer` with this IPTC metadata,
+    /// holding only the metadata segments in memory.
+    pub async fn write_to_async_writer<R, W>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        self.write_to_async_writer_with(reader, writer, &WriteOptions::default())
+            .await
+    }
+
+    /// Copies a JPEG image from `reader` to `writer` with this IPTC metadata,
+    /// written according to `options`.
+    pub async fn write_to_async_writer_with<R, W>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        options: &WriteOptions,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut datasets = self.datasets();
+        validate::apply_policy(options.policy, &self.data, &mut datasets)?;
+
+        let mut walker = SegmentWalker::new(None);
+        walk_segments(&mut reader, &mut walker, |_| None::<()>).await?;
+        let (header, image) = walker.into_header();
+
+        let header = JPEGReader::write_iptc_header(header, &datasets, options.padding)?;
+        writer.write_all(&header).await?;
+
+        if let Some(marker) = image {
+            writer.write_all(&marker).await?;
+            tokio::io::copy(&mut reader, &mut writer).await?;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+// Decodes the Photoshop segment, with dataset offsets from the start of the
+// stream
+fn read_segment(segment: SegmentRef) -> Result<IIMData, Box<dyn Error>> {
+    let (data, mut datasets) = read_iptc_data(segment.payload, 0, segment.payload.len())?;
+    for dataset in &mut datasets {
+        dataset.offset = dataset.offset.map(|o| o + segment.offset as usize);
+    }
+    Ok((data, datasets))
+}
+
+// Feeds `walker` from an async stream, like `jpeg::walk_segments` does from
+// a blocking one, skipping segments by reading them
+async fn walk_segments<R: AsyncRead + Unpin, T>(
+    reader: &mut R,
+    walker: &mut SegmentWalker,
+    mut found: impl FnMut(SegmentRef) -> Option<T>,
+) -> Result<Option<T>, Box<dyn Error>> {
+    loop {
+        match walker.next() {
+            Step::Read(buffer) => match reader.read_exact(buffer).await {
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => walker.end_of_stream()?,
+                result => {
+                    result?;
+                }
+            },
+            Step::Skip(length) => {
+                let skipped = tokio::io::copy(
+                    &mut (&mut *reader).take(length as u64),
+                    &mut tokio::io::sink(),
+                )
+                .await?;
+                if skipped < length as u64 {
+                    return Err("Unexpected end of file in a JPEG segment".into());
+                }
+            }
+            Step::Done => return Ok(None),
+        }
+        if let Some(result) = walker.advance()?.and_then(&mut found) {
+            return Ok(Some(result));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::WriteOptions;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Temporary names tried before giving up, in case others are taken.
+const TEMP_ATTEMPTS: usize = 16;
+
+/// Replaces the contents of the file at `path` without ever leaving it
+/// half-written: the new contents go to a temporary file in the same
+/// directory, which is synced and then renamed over the original.
+pub(crate) fn replace_file(
+    path: &Path,
+    contents: &[u8],
+    options: &WriteOptions,
+) -> Result<(), Box<dyn Error>> {
+    // Write next to the real file, so that a symlink stays a symlink
+    let path = fs::canonicalize(path)?;
+    let metadata = fs::metadata(&path)?;
+    let (temp_path, file) = create_temp(&path)?;
+
+    let result = write_temp(file, contents, &metadata, options).and_then(|()| {
+        if options.backup {
+            backup(&path)?;
+        }
+        fs::rename(&temp_path, &path)?;
+        sync_dir(&path);
+        Ok(())
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_temp(
+    mut file: File,
+    contents: &[u8],
+    metadata: &fs::Metadata,
+    options: &WriteOptions,
+) -> Result<(), Box<dyn Error>> {
+    file.write_all(contents)?;
+
+    if options.preserve_permissions {
+        file.set_permissions(metadata.permissions())?;
+    }
+    if options.preserve_modified {
+        file.set_modified(metadata.modified()?)?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
+// Creates a file under a hidden name in the same directory, so that the
+// rename can't cross file systems. The name is unique to this call, so that
+// threads writing the same file don't share it, and a name that's already
+// taken, say by a file left over from a crash, is never touched.
+fn create_temp(path: &Path) -> Result<(PathBuf, File), Box<dyn Error>> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file", path.display()))?;
+    for _ in 0..TEMP_ATTEMPTS {
+        let temp_path = path.with_file_name(format!(
+            ".{}.{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(format!("no free temporary name next to {}", path.display()).into())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+// Links the original to its backup name, so that the original is never
+// missing, and copies it where links aren't supported
+fn backup(path: &Path) -> Result<(), Box<dyn Error>> {
+    let backup = backup_path(path);
+    if backup.exists() {
+        fs::remove_file(&backup)?;
+    }
+    if fs::hard_link(path, &backup).is_err() {
+        fs::copy(path, &backup)?;
+    }
+    Ok(())
+}
+
+// Makes the rename itself durable. Not every platform can open a directory,
+// and the file is already safe either way, so errors are ignored.
+fn sync_dir(path: &Path) {
+    if let Some(dir) = path.parent()
+        && let Ok(dir) = File::open(dir)
+    {
+        let _ = dir.sync_all();
+    }
+}
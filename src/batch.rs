@@ -0,0 +1,298 @@
+//! Reading, checking and updating the metadata of many files in parallel.
+//!
+//! ```rust,no_run
+//! use iptc::IPTCTag;
+//! use iptc::batch::Batch;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let results = Batch::new("archive/**/*.jpg")?
+//!     .threads(8)
+//!     .on_progress(|progress| eprintln!("{}/{}", progress.done, progress.total))
+//!     .read();
+//!
+//! for file in results {
+//!     match file.result {
+//!         Ok(iptc) if iptc.get(IPTCTag::CopyrightNotice).is_empty() => {
+//!             println!("{}: no copyright notice", file.path.display())
+//!         }
+//!         Ok(_) => {}
+//!         Err(e) => println!("{}: {}", file.path.display(), e),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::IPTC;
+use glob::{MatchOptions, Pattern};
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Extensions of the files a directory is searched for.
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "tif", "tiff"];
+
+/// An error for one file, which can be sent across threads.
+pub type FileError = Box<dyn Error + Send + Sync>;
+
+/// The outcome for one file of a batch.
+#[derive(Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    /// The metadata that was read, with any changes `apply` made.
+    pub result: Result<IPTC, FileError>,
+    /// Whether the file was written back.
+    pub written: bool,
+}
+
+/// How far a batch has got, passed to the progress callback after each file.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    /// Files finished so far, including this one.
+    pub done: usize,
+    pub total: usize,
+    /// The file that was just finished.
+    pub path: &'a Path,
+    /// Whether it failed.
+    pub failed: bool,
+}
+
+type ProgressFn<'a> = Box<dyn Fn(&Progress) + Send + Sync + 'a>;
+
+/// A set of files to process in parallel.
+///
+/// Files are processed on a thread pool of their own, one thread per CPU
+/// unless `threads` says otherwise. A file that can't be read or written
+/// doesn't stop the others: each file gets its own result, in path order.
+/// Directories that couldn't be searched get a failed result of their own.
+pub struct Batch<'a> {
+    paths: Vec<PathBuf>,
+    /// Directories that couldn't be searched, with why
+    walk_errors: Vec<(PathBuf, String)>,
+    threads: usize,
+    progress: Option<ProgressFn<'a>>,
+}
+
+impl<'a> Batch<'a> {
+    /// Finds the files to process: every JPEG and TIFF file under a
+    /// directory, searched recursively, or the files matching a glob pattern
+    /// like `archive/**/*.jpg`.
+    ///
+    /// Symlinked directories aren't followed, so that a link loop can't make
+    /// the search endless. Only an invalid pattern is an error here.
+    pub fn new(source: &str) -> Result<Self, Box<dyn Error>> {
+        let root = Path::new(source);
+        let mut walk = Walk::default();
+        if root.is_dir() {
+            walk.find(root, None, &|path| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| EXTENSIONS.iter().any(|x| e.eq_ignore_ascii_case(x)))
+            });
+        } else {
+            let pattern = Pattern::new(source)?;
+            // Like `glob::glob`, wildcards don't match across directories
+            let options = MatchOptions {
+                require_literal_separator: true,
+                ..MatchOptions::new()
+            };
+            let base = literal_prefix(root);
+            let accept = |path: &Path| pattern.matches_path_with(path, options);
+            if base.is_dir() {
+                walk.find(&base, depth(root), &accept);
+            } else if base.is_file() && accept(&base) {
+                walk.paths.push(base);
+            }
+        }
+        walk.paths.sort();
+
+        Ok(Batch {
+            walk_errors: walk.errors,
+            ..Self::from_paths(walk.paths)
+        })
+    }
+
+    /// Processes exactly these files, in this order.
+    pub fn from_paths(paths: Vec<PathBuf>) -> Self {
+        Batch {
+            paths,
+            walk_errors: Vec::new(),
+            threads: 0,
+            progress: None,
+        }
+    }
+
+    /// The files the batch will process.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Sets the number of threads. 0, the default, means one per CPU.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Calls `progress` after each file, from the thread that processed it.
+    pub fn on_progress<F: Fn(&Progress) + Send + Sync + 'a>(mut self, progress: F) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Reads the metadata of every file.
+    pub fn read(&self) -> Vec<FileResult> {
+        self.run(|path| {
+            let iptc = IPTC::read_from_path(path).map_err(to_file_error)?;
+            Ok((iptc, false))
+        })
+    }
+
+    /// Reads the metadata of every file and passes it to `apply`, which
+    /// changes it and returns whether it did. Changed files are written back
+    /// with `IPTC::write_to_file`.
+    pub fn apply<F>(&self, apply: F) -> Vec<FileResult>
+    where
+        F: Fn(&Path, &mut IPTC) -> Result<bool, Box<dyn Error>> + Sync,
+    {
+        self.run(|path| {
+            let mut iptc = IPTC::read_from_path(path).map_err(to_file_error)?;
+            let changed = apply(path, &mut iptc).map_err(to_file_error)?;
+            if changed {
+                iptc.write_to_file(path).map_err(to_file_error)?;
+            }
+            Ok((iptc, changed))
+        })
+    }
+
+    fn run<F>(&self, process: F) -> Vec<FileResult>
+    where
+        F: Fn(&Path) -> Result<(IPTC, bool), FileError> + Sync,
+    {
+        let done = AtomicUsize::new(0);
+        let process_one = |path: &PathBuf| {
+            let result = process(path);
+            if let Some(progress) = &self.progress {
+                progress(&Progress {
+                    done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                    total: self.paths.len(),
+                    path,
+                    failed: result.is_err(),
+                });
+            }
+
+            let (result, written) = match result {
+                Ok((iptc, written)) => (Ok(iptc), written),
+                Err(e) => (Err(e), false),
+            };
+            FileResult {
+                path: path.clone(),
+                result,
+                written,
+            }
+        };
+
+        let mut results: Vec<FileResult> =
+            match ThreadPoolBuilder::new().num_threads(self.threads).build() {
+                Ok(pool) => pool.install(|| self.paths.par_iter().map(process_one).collect()),
+                // Without threads, one file at a time still does the job
+                Err(_) => self.paths.iter().map(process_one).collect(),
+            };
+
+        if !self.walk_errors.is_empty() {
+            results.extend(self.walk_errors.iter().map(|(path, error)| FileResult {
+                path: path.clone(),
+                result: Err(error.as_str().into()),
+                written: false,
+            }));
+            results.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        results
+    }
+}
+
+fn to_file_error(error: Box<dyn Error>) -> FileError {
+    error.to_string().into()
+}
+
+/// Files found so far, and the directories that couldn't be read.
+#[derive(Default)]
+struct Walk {
+    paths: Vec<PathBuf>,
+    errors: Vec<(PathBuf, String)>,
+}
+
+impl Walk {
+    // Searches `dir` recursively for the files `accept` takes, without
+    // following symlinked directories, and at most `depth` directories down
+    fn find(&mut self, dir: &Path, depth: Option<usize>, accept: &dyn Fn(&Path) -> bool) {
+        // An empty path is the current directory, whose entries are listed
+        // without a "./" prefix so that they match patterns like "*.jpg"
+        let listed = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let entries = match fs::read_dir(listed) {
+            Ok(entries) => entries,
+            Err(e) => return self.errors.push((dir.to_path_buf(), e.to_string())),
+        };
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.errors.push((dir.to_path_buf(), e.to_string()));
+                    continue;
+                }
+            };
+            let path = dir.join(entry.file_name());
+            let file_type = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata.file_type(),
+                Err(e) => {
+                    self.errors.push((path, e.to_string()));
+                    continue;
+                }
+            };
+
+            if file_type.is_dir() {
+                if depth != Some(0) {
+                    self.find(&path, depth.map(|depth| depth - 1), accept);
+                }
+            } else if file_type.is_symlink() && path.is_dir() {
+                continue;
+            } else if accept(&path) {
+                self.paths.push(path);
+            }
+        }
+    }
+}
+
+// The leading part of a glob pattern without wildcards, where the search
+// starts
+fn literal_prefix(pattern: &Path) -> PathBuf {
+    pattern.components().take_while(is_literal).collect()
+}
+
+// How many directories below the literal prefix a glob pattern can match
+// files in, or `None` when `**` matches at any depth
+fn depth(pattern: &Path) -> Option<usize> {
+    let wild: Vec<Component> = pattern.components().skip_while(is_literal).collect();
+    if wild
+        .iter()
+        .any(|component| component.as_os_str().to_string_lossy().contains("**"))
+    {
+        return None;
+    }
+    // The last component names the files
+    Some(wild.len().saturating_sub(1))
+}
+
+fn is_literal(component: &Component) -> bool {
+    match component {
+        Component::Normal(name) => !name.to_string_lossy().contains(['*', '?', '[']),
+        _ => true,
+    }
+}
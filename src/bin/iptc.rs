@@ -0,0 +1,161 @@
+use clap::{Parser, Subcommand};
+use iptc::{IPTC, IPTCTag};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Read and write IPTC metadata in JPEG and TIFF files.
+#[derive(Parser)]
+#[command(name = "iptc", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the tags of each file
+    Read {
+        /// Print ExifTool-style JSON instead of text
+        #[arg(long)]
+        json: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Set tags, replacing their values. Repeat a tag to give it several values
+    Set {
+        /// A tag and its value, like Keywords=foo
+        #[arg(short, long = "tag", value_name = "TAG=VALUE", required = true, value_parser = parse_assignment)]
+        tags: Vec<(IPTCTag, String)>,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Remove tags
+    Remove {
+        /// A tag name, like Caption
+        #[arg(short, long = "tag", value_name = "TAG", required = true)]
+        tags: Vec<IPTCTag>,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Remove every tag
+    Strip {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Copy every tag from one file to others
+    Copy {
+        #[arg(long, value_name = "FILE")]
+        from: PathBuf,
+        #[arg(long, value_name = "FILE", required = true, num_args = 1..)]
+        to: Vec<PathBuf>,
+    },
+}
+
+fn parse_assignment(text: &str) -> Result<(IPTCTag, String), String> {
+    let (tag, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected TAG=VALUE, found {:?}", text))?;
+    let tag = tag.trim().parse::<IPTCTag>().map_err(|e| e.to_string())?;
+    Ok((tag, value.to_string()))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let mut failed = false;
+    let mut report = |path: &Path, result: Result<(), Box<dyn Error>>| {
+        if let Err(error) = result {
+            eprintln!("iptc: {}: {}", path.display(), error);
+            failed = true;
+        }
+    };
+
+    match cli.command {
+        Command::Read { json, files } => {
+            let mut objects = Vec::new();
+            for (i, path) in files.iter().enumerate() {
+                let result = IPTC::read_from_path(path).map(|iptc| {
+                    if json {
+                        objects.push(iptc.to_exiftool_json(&path.to_string_lossy()));
+                    } else {
+                        if i > 0 {
+                            println!();
+                        }
+                        print_text(path, &iptc, files.len() > 1);
+                    }
+                });
+                report(path, result);
+            }
+            if json {
+                match serde_json::to_string_pretty(&objects) {
+                    Ok(output) => println!("{}", output),
+                    Err(error) => report(Path::new("-"), Err(error.into())),
+                }
+            }
+        }
+        Command::Set { tags, files } => {
+            for path in &files {
+                report(path, update(path, |iptc| set_tags(iptc, &tags)));
+            }
+        }
+        Command::Remove { tags, files } => {
+            for path in &files {
+                report(
+                    path,
+                    update(path, |iptc| {
+                        for tag in &tags {
+                            iptc.data.remove(tag);
+                        }
+                    }),
+                );
+            }
+        }
+        Command::Strip { files } => {
+            for path in &files {
+                report(path, IPTC::new().write_to_file(path));
+            }
+        }
+        Command::Copy { from, to } => match IPTC::read_from_path(&from) {
+            Ok(iptc) => {
+                for path in &to {
+                    report(path, iptc.write_to_file(path));
+                }
+            }
+            Err(error) => report(&from, Err(error)),
+        },
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn print_text(path: &Path, iptc: &IPTC, header: bool) {
+    if header {
+        println!("======== {}", path.display());
+    }
+    for tag in IPTCTag::iter() {
+        if let Some(values) = iptc.data.get(&tag) {
+            println!("{:<32}: {}", tag.to_string(), values.join(", "));
+        }
+    }
+}
+
+fn update(path: &Path, change: impl FnOnce(&mut IPTC)) -> Result<(), Box<dyn Error>> {
+    let mut iptc = IPTC::read_from_path(path)?;
+    change(&mut iptc);
+    iptc.write_to_file(path)
+}
+
+// Each tag given on the command line replaces all of its values
+fn set_tags(iptc: &mut IPTC, tags: &[(IPTCTag, String)]) {
+    for (tag, _) in tags {
+        iptc.data.remove(tag);
+    }
+    for (tag, value) in tags {
+        iptc.data.entry(*tag).or_default().push(value.clone());
+    }
+}
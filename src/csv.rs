@@ -0,0 +1,283 @@
+//! Batch export and import of metadata as CSV, one row per file and one
+//! column per tag, for editing in a spreadsheet.
+//!
+//! ```rust,no_run
+//! use iptc::csv::{self, CsvOptions};
+//! use std::fs::File;
+//! use std::path::Path;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let options = CsvOptions::default();
+//! csv::export_files(File::create("captions.csv")?, &["a.jpg", "b.jpg"], &options)?;
+//!
+//! // Later, after editing the captions
+//! for change in csv::plan_import(File::open("captions.csv")?, Path::new("."), &options)? {
+//!     println!("{}", change);
+//! }
+//! csv::import(File::open("captions.csv")?, Path::new("."), &options)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::IPTC;
+use crate::tags::IPTCTag;
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const FILE_COLUMN: &str = "File";
+
+/// Options for CSV export and import.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Joins the values of repeatable tags in a cell on export, and splits
+    /// them on import. Backslashes and separator characters inside those
+    /// values are escaped with a backslash.
+    pub separator: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            separator: "; ".to_string(),
+        }
+    }
+}
+
+/// A tag that an import changes, or would change in a dry run.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CsvChange {
+    pub file: PathBuf,
+    pub tag: IPTCTag,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+}
+
+impl fmt::Display for CsvChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {:?} -> {:?}",
+            self.file.display(),
+            self.tag,
+            self.old.join(", "),
+            self.new.join(", ")
+        )
+    }
+}
+
+/// Writes one row per file, with a "File" column followed by one column per
+/// tag.
+pub fn export<'a, W, P, I>(writer: W, files: I, options: &CsvOptions) -> Result<(), Box<dyn Error>>
+where
+    W: Write,
+    P: AsRef<Path>,
+    I: IntoIterator<Item = (P, &'a IPTC)>,
+{
+    let mut csv_writer = ::csv::Writer::from_writer(writer);
+
+    let mut header = vec![FILE_COLUMN.to_string()];
+    header.extend(IPTCTag::iter().map(|tag| tag.to_string()));
+    csv_writer.write_record(&header)?;
+
+    for (path, iptc) in files {
+        let mut row = vec![path.as_ref().to_string_lossy().into_owned()];
+        row.extend(IPTCTag::iter().map(|tag| {
+            iptc.data
+                .get(&tag)
+                .map(|values| join_cell(tag, values, options))
+                .unwrap_or_default()
+        }));
+        csv_writer.write_record(&row)?;
+    }
+
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Reads each file and writes its metadata as a CSV row.
+pub fn export_files<W, P>(
+    writer: W,
+    paths: &[P],
+    options: &CsvOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    W: Write,
+    P: AsRef<Path>,
+{
+    let files = paths
+        .iter()
+        .map(|path| Ok((path, IPTC::read_from_path(path.as_ref())?)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    export(
+        writer,
+        files.iter().map(|(path, iptc)| (path, iptc)),
+        options,
+    )
+}
+
+/// Reports what `import` would change, without writing anything.
+pub fn plan_import<R: Read>(
+    reader: R,
+    base_dir: &Path,
+    options: &CsvOptions,
+) -> Result<Vec<CsvChange>, Box<dyn Error>> {
+    Ok(read_changes(reader, base_dir, options)?
+        .into_iter()
+        .flat_map(|file| file.changes)
+        .collect())
+}
+
+/// Applies a CSV to the files it lists and returns what changed.
+///
+/// Rows are matched to files by the "File" column, relative to `base_dir`.
+/// Only the tag columns present in the CSV are touched, and an empty cell
+/// removes the tag. Files without changes are not rewritten.
+pub fn import<R: Read>(
+    reader: R,
+    base_dir: &Path,
+    options: &CsvOptions,
+) -> Result<Vec<CsvChange>, Box<dyn Error>> {
+    let mut applied = Vec::new();
+    for file in read_changes(reader, base_dir, options)? {
+        if !file.changes.is_empty() {
+            file.iptc.write_to_file(&file.path)?;
+            applied.extend(file.changes);
+        }
+    }
+    Ok(applied)
+}
+
+// A file listed in the CSV, with its row applied
+struct ImportedFile {
+    path: PathBuf,
+    iptc: IPTC,
+    changes: Vec<CsvChange>,
+}
+
+fn read_changes<R: Read>(
+    reader: R,
+    base_dir: &Path,
+    options: &CsvOptions,
+) -> Result<Vec<ImportedFile>, Box<dyn Error>> {
+    let mut csv_reader = ::csv::Reader::from_reader(reader);
+
+    let headers = csv_reader.headers()?.clone();
+    let file_column = headers
+        .iter()
+        .position(|header| header == FILE_COLUMN)
+        .ok_or("CSV has no File column")?;
+    let columns = headers
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != file_column)
+        .map(|(i, header)| Ok((i, header.parse::<IPTCTag>()?)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let mut files = Vec::new();
+    for record in csv_reader.records() {
+        let record = record?;
+        let file = record.get(file_column).ok_or("Row without a file")?;
+        let path = base_dir.join(file);
+        let mut iptc = IPTC::read_from_path(&path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        let mut changes = Vec::new();
+        for (i, tag) in &columns {
+            let cell = record.get(*i).unwrap_or_default();
+            let new = split_cell(*tag, cell, options);
+            let old = iptc.data.get(tag).cloned().unwrap_or_default();
+            if new == old {
+                continue;
+            }
+
+            if new.is_empty() {
+                iptc.data.remove(tag);
+            } else {
+                iptc.data.insert(*tag, new.clone());
+            }
+            changes.push(CsvChange {
+                file: path.clone(),
+                tag: *tag,
+                old,
+                new,
+            });
+        }
+        files.push(ImportedFile {
+            path,
+            iptc,
+            changes,
+        });
+    }
+
+    Ok(files)
+}
+
+// The separator that splits a cell of this tag, if it is repeatable
+fn cell_separator(tag: IPTCTag, options: &CsvOptions) -> Option<&str> {
+    let repeatable = tag.info().is_some_and(|info| info.repeatable);
+    let separator = options.separator.trim();
+    (repeatable && !separator.is_empty()).then_some(separator)
+}
+
+fn join_cell(tag: IPTCTag, values: &[String], options: &CsvOptions) -> String {
+    let Some(separator) = cell_separator(tag, options) else {
+        return values.join(&options.separator);
+    };
+
+    // Escape anything that split_cell would take for a separator
+    let escape = |value: &String| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if c == '\\' || separator.contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    values
+        .iter()
+        .map(escape)
+        .collect::<Vec<_>>()
+        .join(&options.separator)
+}
+
+fn split_cell(tag: IPTCTag, cell: &str, options: &CsvOptions) -> Vec<String> {
+    if cell.is_empty() {
+        return Vec::new();
+    }
+
+    let Some(separator) = cell_separator(tag, options) else {
+        return vec![cell.to_string()];
+    };
+
+    let mut values = Vec::new();
+    let mut value = String::new();
+    let mut rest = cell;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix(separator) {
+            values.push(std::mem::take(&mut value));
+            rest = after;
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        if c == '\\'
+            && let Some(escaped) = rest.chars().next()
+        {
+            value.push(escaped);
+            rest = &rest[escaped.len_utf8()..];
+            continue;
+        }
+        value.push(c);
+    }
+    values.push(value);
+
+    values
+        .into_iter()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
@@ -0,0 +1,283 @@
+use crate::IPTC;
+use crate::tags::IPTCTag;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How one tag differs between two `IPTC` instances.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Change {
+    Added {
+        tag: IPTCTag,
+        values: Vec<String>,
+    },
+    Removed {
+        tag: IPTCTag,
+        values: Vec<String>,
+    },
+    /// The values differ, including only in their order.
+    Changed {
+        tag: IPTCTag,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    /// A dataset that no tag covers, compared byte for byte. `old` or `new`
+    /// is empty when only the other instance has it.
+    Dataset {
+        record: u8,
+        dataset: u8,
+        old: Vec<Vec<u8>>,
+        new: Vec<Vec<u8>>,
+    },
+}
+
+impl Change {
+    /// The tag that changed, or `IPTCTag::Null` for a dataset no tag covers.
+    pub fn tag(&self) -> IPTCTag {
+        match self {
+            Change::Added { tag, .. }
+            | Change::Removed { tag, .. }
+            | Change::Changed { tag, .. } => *tag,
+            Change::Dataset { .. } => IPTCTag::Null,
+        }
+    }
+
+    /// Values that are only in the new version. Datasets no tag covers have
+    /// no text values, so none are listed for them.
+    pub fn added_values(&self) -> Vec<&str> {
+        match self {
+            Change::Added { values, .. } => values.iter().map(String::as_str).collect(),
+            Change::Removed { .. } | Change::Dataset { .. } => Vec::new(),
+            Change::Changed { old, new, .. } => missing_from(new, old),
+        }
+    }
+
+    /// Values that are only in the old version. Datasets no tag covers have
+    /// no text values, so none are listed for them.
+    pub fn removed_values(&self) -> Vec<&str> {
+        match self {
+            Change::Added { .. } | Change::Dataset { .. } => Vec::new(),
+            Change::Removed { values, .. } => values.iter().map(String::as_str).collect(),
+            Change::Changed { old, new, .. } => missing_from(old, new),
+        }
+    }
+
+    /// Whether the values are the same but in a different order.
+    pub fn is_reordered(&self) -> bool {
+        match self {
+            Change::Changed { old, new, .. } => {
+                let (mut old, mut new) = (old.clone(), new.clone());
+                old.sort();
+                new.sort();
+                old == new
+            }
+            Change::Dataset { old, new, .. } => {
+                let (mut old, mut new) = (old.clone(), new.clone());
+                old.sort();
+                new.sort();
+                old == new
+            }
+            _ => false,
+        }
+    }
+}
+
+// Values of `values` that `other` doesn't have, counting repeats
+fn missing_from<'a>(values: &'a [String], other: &[String]) -> Vec<&'a str> {
+    let mut other: Vec<&String> = other.iter().collect();
+    values
+        .iter()
+        .filter(|value| match other.iter().position(|o| o == value) {
+            Some(i) => {
+                other.remove(i);
+                false
+            }
+            None => true,
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+/// The differences between two `IPTC` instances, in tag registry order, then
+/// the datasets no tag covers by record and dataset number.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Renders the diff as JSON, with "added", "removed" and "changed"
+    /// objects keyed by tag name.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::{Map, Value, json};
+
+        let (mut added, mut removed, mut changed) = (Map::new(), Map::new(), Map::new());
+        for change in &self.changes {
+            let tag = change.tag().to_string();
+            match change {
+                Change::Dataset {
+                    record,
+                    dataset,
+                    old,
+                    new,
+                } => {
+                    let key = format!("{}:{}", record, dataset);
+                    let (old, new) = (lossy(old), lossy(new));
+                    match (old.is_empty(), new.is_empty()) {
+                        (true, _) => added.insert(key, json!(new)),
+                        (_, true) => removed.insert(key, json!(old)),
+                        _ => changed.insert(key, json!({ "old": old, "new": new })),
+                    };
+                }
+                Change::Added { values, .. } => {
+                    added.insert(tag, json!(values));
+                }
+                Change::Removed { values, .. } => {
+                    removed.insert(tag, json!(values));
+                }
+                Change::Changed { old, new, .. } => {
+                    changed.insert(
+                        tag,
+                        json!({
+                            "old": old,
+                            "new": new,
+                            "added": change.added_values(),
+                            "removed": change.removed_values(),
+                            "reordered": change.is_reordered(),
+                        }),
+                    );
+                }
+            }
+        }
+
+        json!({
+            "added": Value::Object(added),
+            "removed": Value::Object(removed),
+            "changed": Value::Object(changed),
+        })
+    }
+}
+
+/// One line per change: `+` for added tags, `-` for removed ones and `~` for
+/// changed ones. Datasets no tag covers are shown by number, with invalid
+/// UTF-8 replaced.
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                Change::Dataset {
+                    record,
+                    dataset,
+                    old,
+                    new,
+                } => {
+                    let (added, removed) = (old.is_empty(), new.is_empty());
+                    let (old, new) = (lossy(old).join(", "), lossy(new).join(", "));
+                    match (added, removed) {
+                        (true, _) => writeln!(f, "+ {}:{}: {}", record, dataset, new)?,
+                        (_, true) => writeln!(f, "- {}:{}: {}", record, dataset, old)?,
+                        _ => writeln!(f, "~ {}:{}: {} -> {}", record, dataset, old, new)?,
+                    }
+                }
+                Change::Added { tag, values } => writeln!(f, "+ {}: {}", tag, values.join(", "))?,
+                Change::Removed { tag, values } => writeln!(f, "- {}: {}", tag, values.join(", "))?,
+                Change::Changed { tag, old, new } => {
+                    write!(f, "~ {}: {} -> {}", tag, old.join(", "), new.join(", "))?;
+
+                    let mut notes = Vec::new();
+                    let (added, removed) = (change.added_values(), change.removed_values());
+                    if old.len() > 1 || new.len() > 1 {
+                        if !added.is_empty() {
+                            notes.push(format!("added {}", added.join(", ")));
+                        }
+                        if !removed.is_empty() {
+                            notes.push(format!("removed {}", removed.join(", ")));
+                        }
+                    }
+                    if change.is_reordered() {
+                        notes.push("reordered".to_string());
+                    }
+                    if !notes.is_empty() {
+                        write!(f, " ({})", notes.join("; "))?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IPTC {
+    /// Lists what changed from `self` to `other`, tag by tag. The values of
+    /// repeatable tags are compared in order, so reordering them is a change.
+    pub fn diff(&self, other: &IPTC) -> Diff {
+        let empty = Vec::new();
+        let changes = IPTCTag::iter()
+            .filter_map(|tag| {
+                let old = self.data.get(&tag).unwrap_or(&empty);
+                let new = other.data.get(&tag).unwrap_or(&empty);
+                match (old.is_empty(), new.is_empty()) {
+                    _ if old == new => None,
+                    (true, _) => Some(Change::Added {
+                        tag,
+                        values: new.clone(),
+                    }),
+                    (_, true) => Some(Change::Removed {
+                        tag,
+                        values: old.clone(),
+                    }),
+                    _ => Some(Change::Changed {
+                        tag,
+                        old: old.clone(),
+                        new: new.clone(),
+                    }),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Diff {
+            changes: changes
+                .into_iter()
+                .chain(self.diff_datasets(other))
+                .collect(),
+        }
+    }
+
+    // Compares the datasets no tag covers, value by value in file order
+    fn diff_datasets(&self, other: &IPTC) -> Vec<Change> {
+        type Values = (Vec<Vec<u8>>, Vec<Vec<u8>>);
+        let mut datasets: BTreeMap<(u8, u8), Values> = BTreeMap::new();
+        for d in self.datasets.iter().filter(|d| d.tag().is_none()) {
+            let (old, _) = datasets.entry((d.record, d.dataset)).or_default();
+            old.push(d.value.clone());
+        }
+        for d in other.datasets.iter().filter(|d| d.tag().is_none()) {
+            let (_, new) = datasets.entry((d.record, d.dataset)).or_default();
+            new.push(d.value.clone());
+        }
+
+        datasets
+            .into_iter()
+            .filter(|(_, (old, new))| old != new)
+            .map(|((record, dataset), (old, new))| Change::Dataset {
+                record,
+                dataset,
+                old,
+                new,
+            })
+            .collect()
+    }
+}
+
+// Raw values as text, with invalid UTF-8 replaced
+fn lossy(values: &[Vec<u8>]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+        .collect()
+}
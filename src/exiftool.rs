@@ -0,0 +1,239 @@
+use crate::IPTC;
+use crate::iim::Dataset;
+use crate::tags::{IPTCTag, TagInfo, ValueType};
+use serde_json::{Map, Value};
+use std::error::Error;
+
+// Print conversions ExifTool applies to coded values, as (value, printed) pairs
+const URGENCY: &[(&str, &str)] = &[
+    ("0", "0 (reserved)"),
+    ("1", "1 (most urgent)"),
+    ("5", "5 (normal urgency)"),
+    ("8", "8 (least urgent)"),
+    ("9", "9 (user-defined priority)"),
+];
+const ACTION_ADVISED: &[(&str, &str)] = &[
+    ("01", "Object Kill"),
+    ("02", "Object Replace"),
+    ("03", "Object Append"),
+    ("04", "Object Reference"),
+];
+const OBJECT_CYCLE: &[(&str, &str)] = &[
+    ("a", "Morning"),
+    ("p", "Evening"),
+    ("b", "Both Morning and Evening"),
+];
+const IMAGE_ORIENTATION: &[(&str, &str)] =
+    &[("P", "Portrait"), ("L", "Landscape"), ("S", "Square")];
+const CODED_CHARACTER_SET: &[(&str, &str)] = &[("\x1b%G", "UTF8")];
+
+fn print_conversions(tag: IPTCTag) -> &'static [(&'static str, &'static str)] {
+    match tag {
+        IPTCTag::Urgency => URGENCY,
+        IPTCTag::ActionAdvised => ACTION_ADVISED,
+        IPTCTag::ObjectCycle => OBJECT_CYCLE,
+        IPTCTag::ImageOrientation => IMAGE_ORIENTATION,
+        IPTCTag::CodedCharacterSet => CODED_CHARACTER_SET,
+        _ => &[],
+    }
+}
+
+impl IPTC {
+    /// Exports the tags in the shape `exiftool -j -G1 -IPTC:all` prints for
+    /// one file: "IPTC:"-prefixed ExifTool names, ExifTool's date, time and
+    /// coded value formatting, numbers as JSON numbers, and arrays only for
+    /// tags with more than one value. Datasets that no `IPTCTag` covers are
+    /// left out, as ExifTool does without `-u`.
+    pub fn to_exiftool_json(&self, source_file: &str) -> Value {
+        let mut object = Map::new();
+        object.insert("SourceFile".to_string(), source_file.into());
+
+        let datasets = self.datasets();
+        for info in TagInfo::all() {
+            let values: Vec<Value> = datasets
+                .iter()
+                .filter(|d| d.record == info.record && d.dataset == info.dataset)
+                .map(|dataset| export_value(info, dataset))
+                .collect();
+
+            let value = match values.len() {
+                0 => continue,
+                1 => values.into_iter().next().unwrap(),
+                _ => Value::Array(values),
+            };
+            object.insert(format!("IPTC:{}", info.exiftool_name), value);
+        }
+
+        Value::Object(object)
+    }
+
+    /// Imports tags from one object of `exiftool -j -G1` output, undoing the
+    /// formatting `to_exiftool_json` applies. Keys of other groups, tags that
+    /// `IPTCTag` doesn't cover and binary placeholders are skipped.
+    pub fn from_exiftool_json(json: &Value) -> Result<Self, Box<dyn Error>> {
+        let object = json
+            .as_object()
+            .ok_or("ExifTool JSON should be an object per file")?;
+        let mut iptc = IPTC::new();
+
+        for (key, value) in object {
+            let name = match key.split_once(':') {
+                Some((group, name)) if group.eq_ignore_ascii_case("IPTC") => name,
+                Some(_) => continue,
+                None if key == "SourceFile" => continue,
+                None => key,
+            };
+            let Some(info) = name.parse::<IPTCTag>().ok().and_then(IPTCTag::info) else {
+                continue;
+            };
+
+            let values = match value {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+            for value in values {
+                if let Some(bytes) = import_value(info, value)? {
+                    iptc.add_dataset(info.record, info.dataset, &bytes);
+                }
+            }
+        }
+
+        Ok(iptc)
+    }
+}
+
+fn export_value(info: &TagInfo, dataset: &Dataset) -> Value {
+    let bytes = &dataset.value;
+    if info.tag == IPTCTag::SizeMode && bytes.len() == 1 {
+        return bytes[0].into();
+    }
+    if info.value_type == ValueType::Binary && info.tag != IPTCTag::CodedCharacterSet {
+        return format!(
+            "(Binary data {} bytes, use -b option to extract)",
+            bytes.len()
+        )
+        .into();
+    }
+
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return format!("base64:{}", base64_encode(bytes)).into();
+    };
+    let text = match info.value_type {
+        ValueType::Short => dataset.decoded(),
+        ValueType::Date if text.len() == 8 => {
+            format!("{}:{}:{}", &text[..4], &text[4..6], &text[6..])
+        }
+        ValueType::Time if text.len() == 11 => format!(
+            "{}:{}:{}{}:{}",
+            &text[..2],
+            &text[2..4],
+            &text[4..6],
+            &text[6..9],
+            &text[9..]
+        ),
+        _ => print_conversions(info.tag)
+            .iter()
+            .find(|(value, _)| *value == text)
+            .map_or(text, |(_, printed)| printed)
+            .to_string(),
+    };
+
+    if is_json_number(&text)
+        && let Ok(number) = text.parse::<serde_json::Number>()
+    {
+        return Value::Number(number);
+    }
+    Value::String(text)
+}
+
+fn import_value(info: &TagInfo, value: &Value) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(value) => value.to_string(),
+        _ => return Err(format!("Unsupported value for {}: {}", info.tag, value).into()),
+    };
+
+    if text.starts_with("(Binary data ") {
+        return Ok(None);
+    }
+    if let Some(encoded) = text.strip_prefix("base64:") {
+        return Ok(Some(base64_decode(encoded)?));
+    }
+    if info.tag == IPTCTag::SizeMode {
+        return Ok(Some(vec![text.parse::<u8>()?]));
+    }
+
+    let text = match info.value_type {
+        ValueType::Short => {
+            let number = text.parse::<u16>()?;
+            return Ok(Some(number.to_be_bytes().to_vec()));
+        }
+        ValueType::Date | ValueType::Time => text.replace(':', ""),
+        _ => print_conversions(info.tag)
+            .iter()
+            .find(|(_, printed)| *printed == text)
+            .map_or(text.as_str(), |(value, _)| value)
+            .to_string(),
+    };
+    Ok(Some(text.into_bytes()))
+}
+
+// ExifTool leaves values unquoted when they look like this
+fn is_json_number(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+
+    let valid_integer = match integer.len() {
+        1 => integer.as_bytes()[0].is_ascii_digit(),
+        2..=15 => integer.bytes().all(|b| b.is_ascii_digit()) && !integer.starts_with('0'),
+        _ => false,
+    };
+    let valid_fraction = fraction.is_none_or(|fraction| {
+        (1..=16).contains(&fraction.len()) && fraction.bytes().all(|b| b.is_ascii_digit())
+    });
+
+    valid_integer && valid_fraction
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    let mut n = 0u32;
+    let mut bits = 0;
+    for c in encoded.bytes().filter(|&c| c != b'=') {
+        let value = BASE64
+            .iter()
+            .position(|&b| b == c)
+            .ok_or("Invalid base64 value")?;
+        n = (n << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((n >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
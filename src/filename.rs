@@ -0,0 +1,332 @@
+use crate::IPTC;
+use crate::tags::IPTCTag;
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Builds file names and paths from tag values, for patterns like
+/// `{DateCreated}_{City}_{ByLine}_{seq}.jpg`.
+///
+/// A placeholder is a tag name, `seq` for the position of the file in a batch,
+/// or `stem` and `ext` for the parts of the original file name. Filters follow
+/// the name, separated by `|`:
+///
+/// - `default:text` is used when the tag has no value, or none is left once
+///   it is sanitised; without it, a missing tag is an error
+/// - `date:%Y/%m` reformats a date (`%Y`, `%y`, `%m`, `%d`) or time (`%H`,
+///   `%M`, `%S`) value
+/// - `max:20` truncates the value to 20 characters
+/// - `pad:4` pads numbers with zeros to 4 digits
+///
+/// Values are sanitised so that they can't add directories or characters
+/// that file systems reject, while the pattern itself can contain `/`. Names
+/// that are absolute, contain `..` or an empty directory are refused.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FilenameFormatter {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder { field: Field, filters: Vec<Filter> },
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Field {
+    Tag(IPTCTag),
+    Seq,
+    Stem,
+    Ext,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Filter {
+    Default(String),
+    Date(String),
+    Max(usize),
+    Pad(usize),
+}
+
+/// What to do when two files would get the same name, or the name is taken.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Collision {
+    /// Refuse to rename anything.
+    #[default]
+    Fail,
+    /// Add `_1`, `_2` and so on before the extension.
+    Suffix,
+}
+
+/// A file and the name it gets.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl FilenameFormatter {
+    /// Parses a pattern, checking its placeholders and filters.
+    pub fn new(pattern: &str) -> Result<Self, Box<dyn Error>> {
+        let mut segments = Vec::new();
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed placeholder in {:?}", pattern))?;
+            segments.push(parse_placeholder(&rest[start + 1..start + end])?);
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        Ok(FilenameFormatter { segments })
+    }
+
+    /// Expands the pattern for one file. `path` is the file's current path,
+    /// used for `stem` and `ext`, and `seq` its position in the batch.
+    pub fn format(&self, iptc: &IPTC, path: &Path, seq: usize) -> Result<String, Box<dyn Error>> {
+        let mut name = String::new();
+
+        for segment in &self.segments {
+            let (field, filters) = match segment {
+                Segment::Literal(text) => {
+                    name.push_str(text);
+                    continue;
+                }
+                Segment::Placeholder { field, filters } => (field, filters),
+            };
+
+            let value = match field {
+                Field::Tag(tag) => iptc.get(*tag),
+                Field::Seq => seq.to_string(),
+                Field::Stem => os_str(path.file_stem()),
+                Field::Ext => os_str(path.extension()),
+            };
+            let default = filters.iter().find_map(|filter| match filter {
+                Filter::Default(default) => Some(default),
+                _ => None,
+            });
+            // A value like ".." sanitises to nothing, so the default applies
+            // to what is left after sanitising
+            let mut value = sanitize(&value);
+            if value.is_empty() {
+                value = default.map(|default| sanitize(default)).unwrap_or_default();
+            }
+            if value.is_empty() {
+                return Err(
+                    format!("{}: no value for {}", path.display(), field_name(field)).into(),
+                );
+            }
+
+            for filter in filters {
+                value = match filter {
+                    Filter::Default(_) => value,
+                    Filter::Date(format) => format_date(&value, format).ok_or_else(|| {
+                        format!("{}: {:?} is not a date or time", path.display(), value)
+                    })?,
+                    // Cutting can leave a dot or space at the end again
+                    Filter::Max(length) => {
+                        sanitize(&value.chars().take(*length).collect::<String>())
+                    }
+                    Filter::Pad(width) => format!("{:0>width$}", value, width = width),
+                };
+            }
+            name.push_str(&value);
+        }
+
+        check_relative(&name).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(name)
+    }
+
+    /// Works out the new name of each file, in the same directory, without
+    /// renaming anything. Files are numbered from 1 for `seq`.
+    pub fn plan_renames<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        collision: Collision,
+    ) -> Result<Vec<Rename>, Box<dyn Error>> {
+        let mut renames: Vec<Rename> = Vec::new();
+        let mut taken: HashSet<PathBuf> = HashSet::new();
+
+        for (i, from) in paths.iter().enumerate() {
+            let from = from.as_ref();
+            let iptc = IPTC::read_from_path(from)?;
+            let name = self.format(&iptc, from, i + 1)?;
+            let mut to = from.parent().unwrap_or(Path::new("")).join(&name);
+
+            let mut suffix = 0;
+            while taken.contains(&to) || (to != from && to.exists()) {
+                if collision == Collision::Fail {
+                    return Err(format!(
+                        "{} would be renamed to {}, which is already taken",
+                        from.display(),
+                        to.display()
+                    )
+                    .into());
+                }
+                suffix += 1;
+                to = with_suffix(&from.parent().unwrap_or(Path::new("")).join(&name), suffix);
+            }
+
+            taken.insert(to.clone());
+            renames.push(Rename {
+                from: from.to_path_buf(),
+                to,
+            });
+        }
+
+        Ok(renames)
+    }
+
+    /// Renames the files, creating the directories the pattern asks for, and
+    /// returns what was renamed. Nothing is renamed if there is a collision
+    /// and `collision` is `Fail`.
+    pub fn rename<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        collision: Collision,
+    ) -> Result<Vec<Rename>, Box<dyn Error>> {
+        let renames = self.plan_renames(paths, collision)?;
+
+        for rename in &renames {
+            if rename.from == rename.to {
+                continue;
+            }
+            if let Some(parent) = rename.to.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Never overwrite a file that appeared since the plan was made
+            if rename.to.exists() {
+                return Err(format!("{} already exists", rename.to.display()).into());
+            }
+            std::fs::rename(&rename.from, &rename.to)?;
+        }
+
+        Ok(renames)
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Segment, Box<dyn Error>> {
+    let mut parts = placeholder.split('|');
+    let name = parts.next().unwrap_or_default().trim();
+    let field = match name {
+        "seq" => Field::Seq,
+        "stem" => Field::Stem,
+        "ext" => Field::Ext,
+        _ => Field::Tag(
+            name.parse()
+                .map_err(|_| format!("Unknown placeholder {{{}}}", name))?,
+        ),
+    };
+
+    let filters = parts
+        .map(|filter| {
+            let (filter_name, argument) = filter.split_once(':').unwrap_or((filter, ""));
+            let number = || {
+                argument
+                    .parse::<usize>()
+                    .map_err(|_| format!("{} needs a number, found {:?}", filter_name, argument))
+            };
+            Ok(match filter_name.trim() {
+                "default" => Filter::Default(argument.to_string()),
+                "date" => Filter::Date(argument.to_string()),
+                "max" => Filter::Max(number()?),
+                "pad" => Filter::Pad(number()?),
+                _ => {
+                    return Err(format!("Unknown filter {:?} for {{{}}}", filter_name, name).into());
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    Ok(Segment::Placeholder { field, filters })
+}
+
+fn field_name(field: &Field) -> String {
+    match field {
+        Field::Tag(tag) => tag.to_string(),
+        Field::Seq => "seq".to_string(),
+        Field::Stem => "stem".to_string(),
+        Field::Ext => "ext".to_string(),
+    }
+}
+
+fn os_str(value: Option<&std::ffi::OsStr>) -> String {
+    value
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Replaces what would add directories or that file systems reject, and the
+// dots and spaces Windows trims from the ends of names
+fn sanitize(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    sanitized.trim_matches(|c| c == '.' || c == ' ').to_string()
+}
+
+// Makes sure a name stays below the directory it is joined to: no root, no
+// empty components and no `..`
+fn check_relative(name: &str) -> Result<(), String> {
+    let escapes = Path::new(name).has_root()
+        || name
+            .split(['/', '\\'])
+            .any(|component| component.is_empty() || component == "..");
+    if escapes {
+        return Err(format!("{:?} is not a relative file name", name));
+    }
+    Ok(())
+}
+
+// Formats a CCYYMMDD date or an HHMMSS±HHMM time with strftime-like tokens
+fn format_date(value: &str, format: &str) -> Option<String> {
+    let digits = value.as_bytes();
+    let is_date = digits.len() == 8 && digits.iter().all(u8::is_ascii_digit);
+    let is_time = digits.len() >= 6 && digits[..6].iter().all(u8::is_ascii_digit);
+    if !is_date && !is_time {
+        return None;
+    }
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        let part = match (chars.next()?, is_date) {
+            ('Y', true) => &value[..4],
+            ('y', true) => &value[2..4],
+            ('m', true) => &value[4..6],
+            ('d', true) => &value[6..8],
+            ('H', false) => &value[..2],
+            ('M', false) => &value[2..4],
+            ('S', false) => &value[4..6],
+            ('%', _) => "%",
+            _ => return None,
+        };
+        formatted.push_str(part);
+    }
+    Some(formatted)
+}
+
+fn with_suffix(path: &Path, suffix: usize) -> PathBuf {
+    let stem = os_str(path.file_stem());
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+    path.with_file_name(name)
+}
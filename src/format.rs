@@ -0,0 +1,100 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::error::Error;
+
+/// An image format, as told by the magic bytes at the start of a file.
+///
+/// Only JPEG and TIFF images can have their metadata read; the others are
+/// recognized so that they can be told apart from files that aren't images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Jpeg,
+    /// TIFF, in either byte order, including BigTIFF.
+    Tiff,
+    Png,
+    WebP,
+    /// Photoshop document.
+    Psd,
+    /// HEIF, including HEIC.
+    Heif,
+    Avif,
+    Gif,
+    /// Encapsulated PostScript, plain or with a DOS binary header.
+    Eps,
+}
+
+impl ImageFormat {
+    /// The equivalent `image` crate format, if `image` has one.
+    #[cfg(feature = "image")]
+    pub fn to_image_format(self) -> Option<image::ImageFormat> {
+        match self {
+            ImageFormat::Jpeg => Some(image::ImageFormat::Jpeg),
+            ImageFormat::Tiff => Some(image::ImageFormat::Tiff),
+            ImageFormat::Png => Some(image::ImageFormat::Png),
+            ImageFormat::WebP => Some(image::ImageFormat::WebP),
+            ImageFormat::Avif => Some(image::ImageFormat::Avif),
+            ImageFormat::Gif => Some(image::ImageFormat::Gif),
+            ImageFormat::Psd | ImageFormat::Heif | ImageFormat::Eps => None,
+        }
+    }
+}
+
+/// Bytes `guess_format` needs to see to tell every format apart.
+#[cfg(feature = "std")]
+pub(crate) const SNIFF_LENGTH: usize = 32;
+
+// Brands of the `ftyp` box that mark HEIF and AVIF files
+const HEIF_BRANDS: &[&[u8]] = &[
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"hevm", b"hevs", b"mif1", b"msf1",
+];
+const AVIF_BRANDS: &[&[u8]] = &[b"avif", b"avis"];
+
+/// Guesses the format of an image from its first bytes.
+pub fn guess_format(buffer: &[u8]) -> Result<ImageFormat, Box<dyn Error>> {
+    let format = if buffer.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        ImageFormat::Jpeg
+    } else if buffer.starts_with(b"II*\0")
+        || buffer.starts_with(b"MM\0*")
+        || buffer.starts_with(b"II+\0")
+        || buffer.starts_with(b"MM\0+")
+    {
+        ImageFormat::Tiff
+    } else if buffer.starts_with(b"\x89PNG\r\n\x1A\n") {
+        ImageFormat::Png
+    } else if buffer.starts_with(b"RIFF") && buffer.get(8..12) == Some(b"WEBP") {
+        ImageFormat::WebP
+    } else if buffer.starts_with(b"8BPS") {
+        ImageFormat::Psd
+    } else if buffer.starts_with(b"GIF87a") || buffer.starts_with(b"GIF89a") {
+        ImageFormat::Gif
+    } else if buffer.starts_with(b"%!PS-Adobe-") || buffer.starts_with(&[0xC5, 0xD0, 0xD3, 0xC6]) {
+        ImageFormat::Eps
+    } else if let Some(format) = guess_iso_format(buffer) {
+        format
+    } else {
+        return Err("Unknown image format".into());
+    };
+    Ok(format)
+}
+
+// Reads the brands of an ISO base media file's leading `ftyp` box: the
+// major brand, then the compatible ones that fit in `buffer`
+fn guess_iso_format(buffer: &[u8]) -> Option<ImageFormat> {
+    if buffer.get(4..8)? != b"ftyp" {
+        return None;
+    }
+    let size = u32::from_be_bytes(buffer.get(0..4)?.try_into().ok()?) as usize;
+    let end = size.min(buffer.len());
+    let major = buffer.get(8..12)?;
+    // Skip the minor version
+    let compatible = buffer.get(16..end).unwrap_or_default().chunks_exact(4);
+
+    let brands: Vec<&[u8]> = core::iter::once(major).chain(compatible).collect();
+    if brands.iter().any(|brand| AVIF_BRANDS.contains(brand)) {
+        Some(ImageFormat::Avif)
+    } else if brands.iter().any(|brand| HEIF_BRANDS.contains(brand)) {
+        Some(ImageFormat::Heif)
+    } else {
+        None
+    }
+}
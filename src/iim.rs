@@ -0,0 +1,342 @@
+//! The IIM dataset codec and the Photoshop IRB parser.
+//!
+//! Nothing here needs more than `alloc`, so these work without the `std`
+//! feature, e.g. to read and write IIM on a device.
+
+use crate::tags::{IPTCTag, ParseFn, TagInfo};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+
+pub(crate) const FIELD_DELIMITER: u8 = 0x1c;
+
+pub(crate) trait ReadUtils {
+    fn read_u16be(&self, offset: usize) -> u16;
+    fn read_u32be(&self, offset: usize) -> u32;
+}
+
+impl ReadUtils for [u8] {
+    fn read_u16be(&self, offset: usize) -> u16 {
+        ((self[offset] as u16) << 8) | (self[offset + 1] as u16)
+    }
+
+    fn read_u32be(&self, offset: usize) -> u32 {
+        ((self.read_u16be(offset) as u32) << 16) | (self.read_u16be(offset + 2) as u32)
+    }
+}
+
+/// A dataset borrowed from the bytes it was read from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DatasetRef<'a> {
+    pub record: u8,
+    pub dataset: u8,
+    pub value: &'a [u8],
+    /// Offset of the dataset header in the walked bytes
+    pub offset: usize,
+}
+
+/// Walks IIM datasets in file order without copying them. Bytes before a
+/// field delimiter are skipped, and a dataset cut short by the end of the
+/// bytes ends the walk.
+#[derive(Debug, Clone)]
+pub(crate) struct DatasetRefs<'a> {
+    iim: &'a [u8],
+    position: usize,
+}
+
+pub(crate) fn dataset_refs(iim: &[u8]) -> DatasetRefs<'_> {
+    DatasetRefs { iim, position: 0 }
+}
+
+impl<'a> Iterator for DatasetRefs<'a> {
+    type Item = DatasetRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let iim = self.iim;
+        let start = iim
+            .get(self.position..)
+            .and_then(|rest| rest.iter().position(|b| *b == FIELD_DELIMITER))
+            .map(|found| self.position + found);
+        let header = start.and_then(|start| iim.get(start..start + 5));
+        let value = header.zip(start).and_then(|(header, start)| {
+            iim.get(start + 5..start + 5 + header.read_u16be(3) as usize)
+        });
+
+        let (Some(start), Some(header), Some(value)) = (start, header, value) else {
+            self.position = iim.len();
+            return None;
+        };
+        self.position = start + 5 + value.len();
+        Some(DatasetRef {
+            record: header[1],
+            dataset: header[2],
+            value,
+            offset: start,
+        })
+    }
+}
+
+/// A Photoshop resource block borrowed from the bytes it was read from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlockRef<'a> {
+    pub resource_id: u16,
+    pub name: &'a [u8],
+    /// Resource data, up to the end of the walked bytes if the block's size
+    /// runs past it
+    pub data: &'a [u8],
+    /// Offset of the data in the walked bytes
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub offset: usize,
+    /// Whether the block's size runs past the end of the walked bytes
+    pub truncated: bool,
+}
+
+/// Walks the resource blocks of an IRB, after the "Photoshop 3.0" signature,
+/// without copying them. Bytes before an "8BIM" signature are skipped, and a
+/// block header cut short is an error that ends the walk.
+#[derive(Debug, Clone)]
+pub(crate) struct BlockRefs<'a> {
+    irb: &'a [u8],
+    position: usize,
+}
+
+pub(crate) fn block_refs(irb: &[u8]) -> BlockRefs<'_> {
+    BlockRefs { irb, position: 0 }
+}
+
+impl<'a> BlockRefs<'a> {
+    // Reads the block whose signature is at `i`, and where the next one
+    // starts
+    fn read_block(&self, i: usize) -> Result<(BlockRef<'a>, usize), &'static str> {
+        let irb = self.irb;
+        // Resource ID, then the name length
+        let header = irb
+            .get(i + 4..i + 7)
+            .ok_or("Invalid offset for name length")?;
+        let resource_id = header.read_u16be(0);
+
+        // Name: Pascal string, padded to make the size even
+        let name_length = header[2] as usize;
+        let name = irb
+            .get(i + 7..i + 7 + name_length)
+            .ok_or("Invalid offset for name")?;
+        let size_offset = i + 6 + ((name_length + 2) & !1);
+
+        let size = irb
+            .get(size_offset..size_offset + 4)
+            .ok_or("Invalid offset for block size")?
+            .read_u32be(0) as usize;
+        let start = size_offset + 4;
+
+        // Resource data is padded to make the size even
+        let next = start
+            .checked_add(size)
+            .and_then(|next| next.checked_add(size % 2))
+            .ok_or("Invalid resource block size")?;
+        let end = (start + size).min(irb.len());
+
+        let block = BlockRef {
+            resource_id,
+            name,
+            data: &irb[start..end],
+            offset: start,
+            truncated: end < start + size,
+        };
+        Ok((block, next))
+    }
+}
+
+impl<'a> Iterator for BlockRefs<'a> {
+    type Item = Result<BlockRef<'a>, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let found = self
+            .irb
+            .get(self.position..)
+            .and_then(|rest| rest.windows(4).position(|w| w == b"8BIM"));
+        let Some(found) = found else {
+            self.position = self.irb.len();
+            return None;
+        };
+
+        match self.read_block(self.position + found) {
+            Ok((block, next)) => {
+                self.position = next;
+                Some(Ok(block))
+            }
+            Err(e) => {
+                self.position = self.irb.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A single IIM dataset, exactly as it is stored in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dataset {
+    pub record: u8,
+    pub dataset: u8,
+    /// Raw value bytes, without the 5 byte dataset header.
+    pub value: Vec<u8>,
+    /// Byte offset of the dataset header in the buffer it was read from,
+    /// `None` for datasets that were added or changed since.
+    pub offset: Option<usize>,
+}
+
+impl Dataset {
+    /// Creates a dataset that isn't backed by a file yet.
+    pub fn new(record: u8, dataset: u8, value: &[u8]) -> Self {
+        Dataset {
+            record,
+            dataset,
+            value: value.to_vec(),
+            offset: None,
+        }
+    }
+
+    /// The `IPTCTag` for this record and dataset number, if there is one.
+    pub fn tag(&self) -> Option<IPTCTag> {
+        TagInfo::find(self.record, self.dataset).map(|info| info.tag)
+    }
+
+    /// Decodes the value the same way as `IPTC::get` does for known tags.
+    /// Unknown datasets are decoded as UTF-8, with invalid bytes replaced.
+    pub fn decoded(&self) -> String {
+        match TagInfo::find(self.record, self.dataset) {
+            Some(info) => decode_value(info.parse_fn(), &self.value),
+            None => String::from_utf8_lossy(&self.value).into_owned(),
+        }
+    }
+}
+
+/// A Photoshop image resource block, from the IRB in an APP13 segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    /// Resource ID, e.g. `IPTC_RESOURCE_ID` for the IIM datasets.
+    pub id: u16,
+    /// Name, usually empty.
+    pub name: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+/// Resource ID of the block holding the IIM datasets.
+pub const IPTC_RESOURCE_ID: u16 = 0x0404;
+
+const PHOTOSHOP_SIGNATURE: &[u8] = b"Photoshop 3.0\0";
+
+/// Reads the datasets of the content of an IPTC resource block, in file
+/// order. Offsets are from the start of `iim`.
+pub fn decode_datasets(iim: &[u8]) -> Vec<Dataset> {
+    dataset_refs(iim)
+        .map(|dataset| Dataset {
+            record: dataset.record,
+            dataset: dataset.dataset,
+            value: dataset.value.to_vec(),
+            offset: Some(dataset.offset),
+        })
+        .collect()
+}
+
+/// Reads every resource block of a Photoshop IRB, starting with the
+/// "Photoshop 3.0" signature, in file order.
+pub fn decode_resources(irb: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+    read_resources(irb, 0, irb.len())
+}
+
+/// Reads every resource block of the IRB at `start` in `buffer`.
+pub(crate) fn read_resources(
+    buffer: &[u8],
+    start: usize,
+    length: usize,
+) -> Result<Vec<Resource>, Box<dyn Error>> {
+    if buffer.get(start..start + 13).ok_or("Invalid slice")? != b"Photoshop 3.0" {
+        return Err("Not valid Photoshop data".into());
+    }
+
+    block_refs(irb_blocks(buffer, start, length))
+        .map(|block| {
+            let block = block?;
+            if block.truncated {
+                return Err("Invalid resource block size".into());
+            }
+            Ok(Resource {
+                id: block.resource_id,
+                name: block.name.to_vec(),
+                data: block.data.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// The resource blocks of the IRB at `start` in `buffer`, after the
+/// "Photoshop 3.0" signature, as far as `length` or the end of `buffer`.
+pub(crate) fn irb_blocks(buffer: &[u8], start: usize, length: usize) -> &[u8] {
+    let end = core::cmp::min(buffer.len(), start.saturating_add(length));
+    buffer.get(start + 13..end).unwrap_or_default()
+}
+
+/// Encodes resource blocks as a Photoshop IRB, ready for an APP13 segment.
+pub fn encode_resources(resources: &[Resource]) -> Vec<u8> {
+    let mut binary = PHOTOSHOP_SIGNATURE.to_vec();
+
+    for resource in resources {
+        binary.extend_from_slice(b"8BIM");
+        binary.extend_from_slice(&resource.id.to_be_bytes());
+
+        // Name: Pascal string, padded to make the size even
+        let name = &resource.name[..resource.name.len().min(255)];
+        binary.push(name.len() as u8);
+        binary.extend_from_slice(name);
+        if name.len() % 2 == 0 {
+            binary.push(0x00);
+        }
+
+        // Resource data, padded to make the size even
+        binary.extend_from_slice(&(resource.data.len() as u32).to_be_bytes());
+        binary.extend_from_slice(&resource.data);
+        if resource.data.len() % 2 != 0 {
+            binary.push(0x00);
+        }
+    }
+
+    binary
+}
+
+/// Longest value a standard dataset can hold. The top bit of the length
+/// field marks an extended dataset, which this crate doesn't write.
+pub const MAX_VALUE_LENGTH: usize = 0x7FFF;
+
+/// Encodes datasets as the payload of the IPTC resource block.
+///
+/// Fails if a value is longer than `MAX_VALUE_LENGTH`.
+pub fn encode_datasets(datasets: &[Dataset]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut iptc_block = Vec::new();
+
+    for dataset in datasets {
+        if dataset.value.len() > MAX_VALUE_LENGTH {
+            return Err(format!(
+                "Dataset {}:{} is {} bytes long, the most IIM allows is {}",
+                dataset.record,
+                dataset.dataset,
+                dataset.value.len(),
+                MAX_VALUE_LENGTH
+            )
+            .into());
+        }
+
+        // Field delimiter, record number and dataset number
+        iptc_block.extend_from_slice(&[FIELD_DELIMITER, dataset.record, dataset.dataset]);
+        // Value length (big endian)
+        iptc_block.extend_from_slice(&(dataset.value.len() as u16).to_be_bytes());
+        iptc_block.extend_from_slice(&dataset.value);
+    }
+
+    Ok(iptc_block)
+}
+
+pub(crate) fn decode_value(parse: ParseFn, raw_bytes: &[u8]) -> String {
+    parse(raw_bytes)
+}
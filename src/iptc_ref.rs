@@ -0,0 +1,130 @@
+use crate::iim::{
+    BlockRefs, DatasetRefs, IPTC_RESOURCE_ID, block_refs, dataset_refs, decode_value,
+};
+use crate::jpeg::JPEGReader;
+use crate::reader::contains_value;
+use crate::tags::IPTCTag;
+use std::error::Error;
+
+/// A read-only view of the IIM datasets of an image that borrows from the
+/// image buffer, for when only a few tags of many images are needed.
+///
+/// Nothing is decoded or copied up front: `datasets` walks the raw bytes,
+/// and `get` decodes just the tag asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct IptcRef<'a> {
+    source: Source<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    /// Photoshop resource blocks, after the signature
+    Resources(&'a [u8]),
+    /// IIM datasets
+    Datasets(&'a [u8]),
+}
+
+impl<'a> IptcRef<'a> {
+    /// Borrows the IIM datasets of a JPEG image. An image without any gives
+    /// an empty view.
+    pub fn from_buffer(buffer: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        if !buffer.starts_with(&[0xFF, 0xD8]) {
+            return Err("Borrowed reading is only supported for JPEG files".into());
+        }
+
+        let resources = JPEGReader::photoshop_segment(buffer).map_or(&[][..], |segment| {
+            // Blocks start after "Photoshop 3.0", like `read_iptc_data` reads them
+            &segment[13..]
+        });
+        Ok(IptcRef {
+            source: Source::Resources(resources),
+        })
+    }
+
+    /// Borrows bare IIM datasets, such as the content of the IPTC resource
+    /// block.
+    pub fn from_iim(iim: &'a [u8]) -> Self {
+        IptcRef {
+            source: Source::Datasets(iim),
+        }
+    }
+
+    /// Iterates over every dataset in file order, as record number, dataset
+    /// number and raw value.
+    pub fn datasets(&self) -> Datasets<'a> {
+        match self.source {
+            Source::Resources(resources) => Datasets {
+                blocks: block_refs(resources),
+                iim: dataset_refs(&[]),
+            },
+            Source::Datasets(iim) => Datasets {
+                blocks: block_refs(&[]),
+                iim: dataset_refs(iim),
+            },
+        }
+    }
+
+    /// Iterates over the raw values of a dataset.
+    pub fn get_raw(&self, record: u8, dataset: u8) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.datasets()
+            .filter(move |(r, d, _)| *r == record && *d == dataset)
+            .map(|(_, _, value)| value)
+    }
+
+    /// Decodes the values of a tag the same way `IPTC::read_from_buffer`
+    /// does: repeated values are dropped, ignoring case, and only the last
+    /// value of a non-repeatable tag is kept.
+    pub fn get_values(&self, tag: IPTCTag) -> Vec<String> {
+        let Some(info) = tag.info() else {
+            return Vec::new();
+        };
+
+        let mut values: Vec<String> = Vec::new();
+        for raw in self.get_raw(info.record, info.dataset) {
+            let value = decode_value(info.parse_fn(), raw);
+            if !info.repeatable {
+                values = vec![value];
+            } else if !contains_value(&values, &value) {
+                values.push(value);
+            }
+        }
+        values
+    }
+
+    /// Decodes a tag's values and joins them like `IPTC::get` does.
+    pub fn get(&self, tag: IPTCTag) -> String {
+        self.get_values(tag).join(", ")
+    }
+
+    /// Whether the image has any IIM datasets.
+    pub fn is_empty(&self) -> bool {
+        self.datasets().next().is_none()
+    }
+}
+
+/// Iterator over the datasets of an `IptcRef`.
+#[derive(Debug, Clone)]
+pub struct Datasets<'a> {
+    /// Resource blocks not visited yet
+    blocks: BlockRefs<'a>,
+    /// Datasets not visited yet, of the current IPTC block
+    iim: DatasetRefs<'a>,
+}
+
+impl<'a> Iterator for Datasets<'a> {
+    type Item = (u8, u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(dataset) = self.iim.next() {
+                return Some((dataset.record, dataset.dataset, dataset.value));
+            }
+            // A block header cut short ends the datasets, like the end of
+            // the blocks does
+            let block = self.blocks.next()?.ok()?;
+            if block.resource_id == IPTC_RESOURCE_ID {
+                self.iim = dataset_refs(block.data);
+            }
+        }
+    }
+}
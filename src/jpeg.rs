@@ -1,277 +1,548 @@
-use crate::reader::ReadUtils;
-use crate::reader::read_iptc_data;
-use crate::tags;
-use crate::tags::TagsMap;
-use crate::tags::{NULL_BLOCK, parse_short};
+use crate::iim::ReadUtils;
+use crate::iim::{
+    Dataset, IPTC_RESOURCE_ID, Resource, encode_datasets, encode_resources, read_resources,
+};
+use crate::reader::{IIMData, read_iptc_data};
 
 use std::collections::HashMap;
 use std::error::Error;
-use tags::IPTCTag;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+
+const APP1: u8 = 0xE1;
+pub(crate) const APP13: u8 = 0xED;
+pub(crate) const PHOTOSHOP_SIGNATURE: &[u8] = b"Photoshop 3.0\0";
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
 
 pub(crate) struct JPEGReader;
 
 impl JPEGReader {
-    pub fn read_iptc(buffer: &[u8]) -> Result<HashMap<IPTCTag, String>, Box<dyn Error>> {
-        let mut offset = 0;
-        offset += 2;
-
-        // Loop through the file looking for the Photoshop header bytes
-        while offset < buffer.len() {
-            if buffer[offset] != 0xFF {
-                return Err(format!(
-                    "Not a valid marker at offset {}, found: {}",
-                    offset, buffer[offset]
-                )
-                .into());
-            }
+    /// Reads the IPTC data from the first APP13 segment holding Photoshop
+    /// resources, skipping any other APP13 segments before it.
+    pub fn read_iptc(buffer: &[u8]) -> Result<IIMData, Box<dyn Error>> {
+        match find_segment(buffer, APP13, PHOTOSHOP_SIGNATURE) {
+            Some((start, length)) => read_iptc_data(buffer, start, length),
+            None => Ok((HashMap::new(), Vec::new())),
+        }
+    }
 
-            let application_marker = buffer[offset + 1];
-
-            if application_marker == 237 {
-                // This is our marker. The content length is 2 byte number.
-                let iptc_data = read_iptc_data(
-                    buffer,
-                    offset + 4,
-                    (&buffer).read_u16be(offset + 2) as usize,
-                )?;
-                return Ok(iptc_data);
-            } else {
-                // Add header length (2 bytes after header type) to offset
-                offset += 2 + (&buffer).read_u16be(offset + 2) as usize;
-            }
+    /// Reads the IPTC data from a JPEG stream, reading only the segment
+    /// headers up to the Photoshop one. Dataset offsets are stream offsets.
+    pub fn read_iptc_from<R: Read + Seek>(reader: &mut R) -> Result<IIMData, Box<dyn Error>> {
+        let Some((offset, payload)) = find_segment_in(reader, APP13, PHOTOSHOP_SIGNATURE)? else {
+            return Ok((HashMap::new(), Vec::new()));
+        };
+
+        let (data, mut datasets) = read_iptc_data(&payload, 0, payload.len())?;
+        for dataset in &mut datasets {
+            dataset.offset = dataset.offset.map(|o| o + offset as usize);
         }
+        Ok((data, datasets))
+    }
 
-        Ok(HashMap::new())
+    /// Reads the Photoshop resource blocks of the APP13 segment, if any.
+    pub fn read_resources(buffer: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+        match find_segment(buffer, APP13, PHOTOSHOP_SIGNATURE) {
+            Some((start, length)) => read_resources(buffer, start, length),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Borrows the payload of the APP13 segment, starting with the Photoshop
+    /// signature, if there is one.
+    pub fn photoshop_segment(buffer: &[u8]) -> Option<&[u8]> {
+        let (start, length) = find_segment(buffer, APP13, PHOTOSHOP_SIGNATURE)?;
+        Some(&buffer[start..start + length])
     }
 
+    /// Reads the XMP packet of the APP1 segment, if any.
+    pub fn read_xmp(buffer: &[u8]) -> Option<Vec<u8>> {
+        let (start, length) = find_segment(buffer, APP1, XMP_SIGNATURE)?;
+        Some(buffer[start + XMP_SIGNATURE.len()..start + length].to_vec())
+    }
+
+    /// Replaces the IPTC resource block, keeping the other resources of the
+    /// APP13 segment. `padding` zero bytes are reserved after the datasets.
     pub fn write_iptc(
         buffer: &[u8],
-        data: &HashMap<IPTCTag, Vec<String>>,
+        datasets: &[Dataset],
+        padding: usize,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        // A damaged IRB is replaced rather than refusing to write
+        let mut resources = Self::read_resources(buffer).unwrap_or_default();
+
+        let position = resources.iter().position(|r| r.id == IPTC_RESOURCE_ID);
+        resources.retain(|r| r.id != IPTC_RESOURCE_ID);
+        if !datasets.is_empty() {
+            resources.insert(
+                position.unwrap_or(resources.len()),
+                Resource {
+                    id: IPTC_RESOURCE_ID,
+                    name: Vec::new(),
+                    data: [encode_datasets(datasets)?, vec![0; padding]].concat(),
+                },
+            );
+        }
+
+        Self::write_resources(buffer, &resources)
+    }
+
+    /// Replaces the APP13 segment with these resource blocks, or removes it
+    /// if there are none.
+    pub fn write_resources(
+        buffer: &[u8],
+        resources: &[Resource],
     ) -> Result<Vec<u8>, Box<dyn Error>> {
-        let mut new_buffer = Vec::new();
+        let payload = (!resources.is_empty()).then(|| encode_resources(resources));
+        replace_segment(buffer, APP13, PHOTOSHOP_SIGNATURE, payload.as_deref())
+    }
 
-        // Copy the initial JPEG marker (SOI)
-        if buffer.len() < 2 || buffer[0] != 0xFF || buffer[1] != 0xD8 {
-            return Err("Not a valid JPEG file".into());
+    /// Copies a JPEG stream with the IPTC resource block replaced, like
+    /// `write_iptc`. Only the segments before the image data are held in
+    /// memory; the image data is copied through in chunks.
+    pub fn write_iptc_to<R: Read, W: Write>(
+        mut reader: R,
+        mut writer: W,
+        datasets: &[Dataset],
+        padding: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut walker = SegmentWalker::new(None);
+        walk_segments(&mut reader, &mut walker, skip_read, |_| None::<()>)?;
+        let (header, image) = walker.into_header();
+
+        writer.write_all(&Self::write_iptc_header(header, datasets, padding)?)?;
+
+        if let Some(marker) = image {
+            writer.write_all(&marker)?;
+            std::io::copy(&mut reader, &mut writer)?;
         }
-        new_buffer.extend_from_slice(&buffer[0..2]);
-        let mut offset = 2;
-
-        // Convert IPTC data to binary format first
-        let iptc_data = Self::convert_iptc_to_binary(data)?;
-        let mut found_app13 = false;
-        let mut inserted_app13 = false;
-
-        // Copy segments until we find SOS
-        while offset + 1 < buffer.len() {
-            // Every JPEG segment must start with 0xFF
-            if buffer[offset] != 0xFF {
-                offset += 1;
-                continue;
-            }
+        writer.flush()?;
+        Ok(())
+    }
 
-            let marker = buffer[offset + 1];
+    /// Replaces the IPTC resource block in `header`, which holds SOI and the
+    /// segments before the image data, like `write_iptc` does.
+    pub fn write_iptc_header(
+        mut header: Vec<u8>,
+        datasets: &[Dataset],
+        padding: usize,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        // A bare SOS marker makes `write_iptc` put a new segment where it
+        // would in a whole file
+        header.extend_from_slice(&[0xFF, 0xDA]);
+        let mut new_header = Self::write_iptc(&header, datasets, padding)?;
+        new_header.truncate(new_header.len() - 2);
+        Ok(new_header)
+    }
 
-            // Skip empty markers
-            if marker == 0xFF {
-                offset += 1;
-                continue;
-            }
+    /// Overwrites the IPTC resource block of a JPEG file where it is, if the
+    /// datasets fit in the space it has, padding any space left with zeros.
+    /// Returns `false`, without writing anything, if they don't.
+    pub fn update_iptc_in_place<F: Read + Write + Seek>(
+        file: &mut F,
+        datasets: &[Dataset],
+    ) -> Result<bool, Box<dyn Error>> {
+        let Some((offset, payload)) = find_segment_in(file, APP13, PHOTOSHOP_SIGNATURE)? else {
+            return Ok(false);
+        };
+        let mut resources = read_resources(&payload, 0, payload.len())?;
+        let Some(iptc) = resources.iter_mut().find(|r| r.id == IPTC_RESOURCE_ID) else {
+            return Ok(false);
+        };
+
+        let data = encode_datasets(datasets)?;
+        if data.len() > iptc.data.len() {
+            return Ok(false);
+        }
+        let length = iptc.data.len();
+        iptc.data = data;
+        iptc.data.resize(length, 0);
+
+        // Anything the encoder would lay out differently needs a rewrite
+        let new_payload = encode_resources(&resources);
+        if new_payload.len() != payload.len() {
+            return Ok(false);
+        }
 
-            // For markers without length field
-            if marker == 0x00 || marker == 0x01 || (marker >= 0xD0 && marker <= 0xD7) {
-                new_buffer.extend_from_slice(&buffer[offset..offset + 2]);
-                offset += 2;
-                continue;
-            }
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&new_payload)?;
+        Ok(true)
+    }
 
-            // End of image marker
-            if marker == 0xD9 {
-                new_buffer.extend_from_slice(&buffer[offset..offset + 2]);
-                break;
-            }
+    /// Replaces the XMP packet, or removes it if `xmp` is `None`.
+    pub fn write_xmp(buffer: &[u8], xmp: Option<&[u8]>) -> Result<Vec<u8>, Box<dyn Error>> {
+        let payload = xmp.map(|xmp| [XMP_SIGNATURE, xmp].concat());
+        replace_segment(buffer, APP1, XMP_SIGNATURE, payload.as_deref())
+    }
+}
 
-            // Start of scan marker - copy the rest of the file
-            if marker == 0xDA {
-                // If we haven't inserted APP13 yet, do it now
-                if !found_app13 && !inserted_app13 {
-                    // Write APP13 marker
-                    new_buffer.extend_from_slice(&[0xFF, 0xED]);
-                    // Write length (including length bytes)
-                    let total_length = (iptc_data.len() + 2) as u16;
-                    new_buffer.push((total_length >> 8) as u8);
-                    new_buffer.push(total_length as u8);
-                    // Write IPTC data
-                    new_buffer.extend_from_slice(&iptc_data);
-                }
+/// Finds the first `marker` segment whose payload starts with `signature`,
+/// returning the offset and length of its payload.
+fn find_segment(buffer: &[u8], marker: u8, signature: &[u8]) -> Option<(usize, usize)> {
+    let mut offset = 2;
+
+    while offset + 4 <= buffer.len() && buffer[offset] == 0xFF {
+        let segment_marker = buffer[offset + 1];
+        // Any number of 0xFF fill bytes can come before a marker
+        if segment_marker == 0xFF {
+            offset += 1;
+            continue;
+        }
 
-                // Copy SOS marker and all remaining data
-                new_buffer.extend_from_slice(&buffer[offset..]);
-                break;
-            }
+        // Metadata segments all come before the image data
+        if segment_marker == 0xDA || segment_marker == 0xD9 {
+            break;
+        }
 
-            // Check if we can read the length
-            if offset + 3 >= buffer.len() {
-                new_buffer.extend_from_slice(&buffer[offset..]);
-                break;
-            }
+        let length = buffer.read_u16be(offset + 2) as usize;
+        let payload = buffer.get(offset + 4..offset + 2 + length.max(2))?;
+        if segment_marker == marker && payload.starts_with(signature) {
+            return Some((offset + 4, payload.len()));
+        }
+        offset += 2 + length;
+    }
+
+    None
+}
 
-            let length = buffer.read_u16be(offset + 2) as usize;
+/// Offset of a segment's payload in a stream, and the payload itself.
+pub(crate) type Segment = (u64, Vec<u8>);
+
+/// Walks the segments of a JPEG stream up to the image data, reading only
+/// their headers, and returns the offset and payload of the first `marker`
+/// segment whose payload starts with `signature`.
+pub(crate) fn find_segment_in<R: Read + Seek>(
+    reader: &mut R,
+    marker: u8,
+    signature: &[u8],
+) -> Result<Option<Segment>, Box<dyn Error>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut walker = SegmentWalker::new(Some(marker));
+    let skip_seek =
+        |reader: &mut R, length: usize| reader.seek(SeekFrom::Current(length as i64)).map(|_| ());
+
+    walk_segments(reader, &mut walker, skip_seek, |segment| {
+        (segment.payload.starts_with(signature)).then(|| (segment.offset, segment.payload.to_vec()))
+    })
+}
 
-            // Validate segment length
-            if length < 2 || offset + 2 + length > buffer.len() {
-                new_buffer.extend_from_slice(&buffer[offset..]);
-                break;
-            }
+/// A segment a `SegmentWalker` has read whole.
+pub(crate) struct SegmentRef<'a> {
+    /// Offset of the payload in the stream
+    pub offset: u64,
+    pub payload: &'a [u8],
+}
 
-            // If this is APP13, replace it with our new data
-            if marker == 0xED {
-                found_app13 = true;
-                // Write APP13 marker
-                new_buffer.extend_from_slice(&[0xFF, 0xED]);
-                // Write length (including length bytes)
-                let total_length = (iptc_data.len() + 2) as u16;
-                new_buffer.push((total_length >> 8) as u8);
-                new_buffer.push(total_length as u8);
-                // Write IPTC data
-                new_buffer.extend_from_slice(&iptc_data);
-                offset += 2 + length;
-            } else {
-                // If we haven't found APP13 and this is after APP0/APP1 but before other segments,
-                // insert our APP13 data here
-                if !found_app13 && !inserted_app13 && marker > 0xE1 {
-                    // Write APP13 marker
-                    new_buffer.extend_from_slice(&[0xFF, 0xED]);
-                    // Write length (including length bytes)
-                    let total_length = (iptc_data.len() + 2) as u16;
-                    new_buffer.push((total_length >> 8) as u8);
-                    new_buffer.push(total_length as u8);
-                    // Write IPTC data
-                    new_buffer.extend_from_slice(&iptc_data);
-                    inserted_app13 = true;
-                }
+/// What a `SegmentWalker` needs from the stream next.
+pub(crate) enum Step<'a> {
+    /// Fill the slice from the stream, then call `advance`
+    Read(&'a mut [u8]),
+    /// Skip this many bytes of the stream, then call `advance`
+    Skip(usize),
+    /// Every segment before the image data has been read
+    Done,
+}
 
-                // Copy the marker and its data
-                new_buffer.extend_from_slice(&buffer[offset..offset + 2 + length]);
-                offset += 2 + length;
-            }
-        }
+#[derive(Debug, Clone, Copy)]
+enum WalkState {
+    Soi,
+    Marker,
+    Length,
+    Payload,
+    Skip(usize),
+    Done,
+}
+
+/// Walks the segments of a JPEG stream up to the image data, without doing
+/// any I/O itself, so that blocking and async streams share it: `next` says
+/// what to read or skip, and `advance` takes it in.
+///
+/// SOI and the segments the walker keeps, all of them or only those with one
+/// marker, are held with their headers, ready to be written back.
+pub(crate) struct SegmentWalker {
+    /// SOI and the segments kept so far, then the bytes asked for
+    header: Vec<u8>,
+    /// How many bytes at the end of `header` were asked for
+    pending: usize,
+    state: WalkState,
+    keep: Option<u8>,
+    /// Bytes of the stream taken in so far
+    position: u64,
+    /// The marker the image data starts with
+    image: Option<[u8; 2]>,
+}
 
-        Ok(new_buffer)
+impl SegmentWalker {
+    /// Starts a walk at SOI, keeping the segments with the `keep` marker, or
+    /// every segment.
+    pub fn new(keep: Option<u8>) -> Self {
+        SegmentWalker {
+            header: vec![0; 2],
+            pending: 2,
+            state: WalkState::Soi,
+            keep,
+            position: 0,
+            image: None,
+        }
     }
 
-    fn convert_iptc_to_binary(
-        data: &HashMap<IPTCTag, Vec<String>>,
-    ) -> Result<Vec<u8>, Box<dyn Error>> {
-        let mut binary = Vec::new();
-        let tags_map = TagsMap::new();
-
-        // Add Photoshop header
-        binary.extend_from_slice(b"Photoshop 3.0\0");
-        binary.push(0x00); // Pad to even length
-
-        // Add 8BIM marker and IPTC block
-        let mut iptc_block = Vec::new();
-
-        // Sort tags by record and dataset numbers
-        let mut sorted_tags: Vec<_> = data.iter().collect();
-        sorted_tags.sort_by_key(|(tag, _)| {
-            if let Some((record, dataset)) = Self::get_record_dataset(tag) {
-                (record, dataset)
-            } else {
-                (0, 0) // Put unknown tags at the start
+    pub fn next(&mut self) -> Step<'_> {
+        match self.state {
+            WalkState::Skip(length) => Step::Skip(length),
+            WalkState::Done => Step::Done,
+            _ => {
+                let start = self.header.len() - self.pending;
+                Step::Read(&mut self.header[start..])
             }
-        });
-
-        // Add IPTC data in sorted order
-        for (tag, values) in sorted_tags {
-            if let Some((record, dataset)) = Self::get_record_dataset(tag) {
-                // Get the tag format
-                let tag_key = format!("{}:{}", record, dataset);
-                let (_, repeatable, parse_fn) = tags_map.get(tag_key).unwrap_or(NULL_BLOCK);
-
-                // Skip empty values
-                if values.is_empty() {
-                    continue;
-                }
+        }
+    }
 
-                let values_to_process: &[String];
+    // Asks for `length` more bytes
+    fn ask(&mut self, state: WalkState, length: usize) {
+        self.header.resize(self.header.len() + length, 0);
+        self.pending = length;
+        self.state = state;
+    }
 
-                // Handle repeatable vs non-repeatable fields
-                if repeatable {
-                    values_to_process = values.as_slice();
+    /// Takes in what `next` asked for, returning the segment it completes,
+    /// if the walker keeps it.
+    pub fn advance(&mut self) -> Result<Option<SegmentRef<'_>>, Box<dyn Error>> {
+        let length = self.header.len();
+        match self.state {
+            WalkState::Soi => {
+                self.position += 2;
+                if self.header != [0xFF, 0xD8] {
+                    return Err("Not a valid JPEG file".into());
+                }
+                self.ask(WalkState::Marker, 2);
+            }
+            WalkState::Marker => {
+                self.position += self.pending as u64;
+                let marker = [self.header[length - 2], self.header[length - 1]];
+                if marker == [0xFF, 0xFF] {
+                    // Any number of 0xFF fill bytes can come before a marker
+                    self.header.pop();
+                    self.ask(WalkState::Marker, 1);
+                } else if ends_header(marker)? {
+                    self.header.truncate(length - 2);
+                    self.image = Some(marker);
+                    self.state = WalkState::Done;
                 } else {
-                    if values.len() > 1 {
-                        println!(
-                            "Warning: Multiple values provided for non-repeatable field {:?}, using only the first value",
-                            tag
-                        );
-                    }
-                    values_to_process = &values[..1];
+                    self.ask(WalkState::Length, 2);
                 }
-
-                // Write each value
-                for value in values_to_process {
-                    // Field delimiter
-                    iptc_block.push(0x1C);
-
-                    // Record number and dataset number
-                    iptc_block.push(record);
-                    iptc_block.push(dataset);
-
-                    // Convert value based on tag format
-                    let value_bytes = if parse_fn as usize == parse_short as usize {
-                        // For short values, convert string to u16 and then to bytes
-                        let num_val = value.parse::<u16>().unwrap_or(0);
-                        vec![(num_val >> 8) as u8, num_val as u8]
-                    } else {
-                        // For regular strings, just use UTF-8 bytes
-                        value.as_bytes().to_vec()
-                    };
-
-                    // Value length (big endian)
-                    let value_len = value_bytes.len() as u16;
-                    iptc_block.push((value_len >> 8) as u8);
-                    iptc_block.push(value_len as u8);
-
-                    // Value
-                    iptc_block.extend_from_slice(&value_bytes);
+            }
+            WalkState::Length => {
+                self.position += 2;
+                let marker = [self.header[length - 4], self.header[length - 3]];
+                let size =
+                    segment_length(marker, [self.header[length - 2], self.header[length - 1]])?;
+                if self.keep.is_none_or(|keep| keep == marker[1]) {
+                    self.ask(WalkState::Payload, size);
+                } else {
+                    self.header.truncate(length - 4);
+                    self.state = WalkState::Skip(size);
                 }
             }
+            WalkState::Payload => {
+                let offset = self.position;
+                self.position += self.pending as u64;
+                let start = length - self.pending;
+                self.ask(WalkState::Marker, 2);
+                return Ok(Some(SegmentRef {
+                    offset,
+                    payload: &self.header[start..length],
+                }));
+            }
+            WalkState::Skip(size) => {
+                self.position += size as u64;
+                self.ask(WalkState::Marker, 2);
+            }
+            WalkState::Done => {}
         }
+        Ok(None)
+    }
 
-        // Add 8BIM marker
-        binary.extend_from_slice(b"8BIM");
+    /// Ends the walk where the stream ends, which must be between segments.
+    pub fn end_of_stream(&mut self) -> Result<(), Box<dyn Error>> {
+        // Drop the part of the segment header that was asked for
+        let partial = match self.state {
+            WalkState::Marker => 2,
+            WalkState::Length => 4,
+            _ => return Err("Unexpected end of file in a JPEG segment".into()),
+        };
+        self.header.truncate(self.header.len() - partial);
+        self.state = WalkState::Done;
+        Ok(())
+    }
 
-        // Resource ID for IPTC (0x0404)
-        binary.extend_from_slice(&[0x04, 0x04]);
+    /// SOI and the segments kept, and the marker the image data starts with,
+    /// unless the stream ended before it.
+    pub fn into_header(self) -> (Vec<u8>, Option<[u8; 2]>) {
+        (self.header, self.image)
+    }
+}
 
-        // Empty name (padded to even length)
-        binary.push(0x00);
-        binary.push(0x00);
+/// Feeds `walker` from a blocking stream until the image data, or until
+/// `found` picks a segment it keeps. `skip` skips the segments it doesn't.
+pub(crate) fn walk_segments<R: Read, T>(
+    reader: &mut R,
+    walker: &mut SegmentWalker,
+    mut skip: impl FnMut(&mut R, usize) -> std::io::Result<()>,
+    mut found: impl FnMut(SegmentRef) -> Option<T>,
+) -> Result<Option<T>, Box<dyn Error>> {
+    loop {
+        match walker.next() {
+            Step::Read(buffer) => match reader.read_exact(buffer) {
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => walker.end_of_stream()?,
+                result => result?,
+            },
+            Step::Skip(length) => skip(reader, length)?,
+            Step::Done => return Ok(None),
+        }
+        if let Some(result) = walker.advance()?.and_then(&mut found) {
+            return Ok(Some(result));
+        }
+    }
+}
 
-        // Block size (big endian)
-        let block_size = iptc_block.len() as u32;
-        binary.extend_from_slice(&[
-            (block_size >> 24) as u8,
-            (block_size >> 16) as u8,
-            (block_size >> 8) as u8,
-            block_size as u8,
-        ]);
+/// Skips `length` bytes of a stream that can't seek by reading them.
+pub(crate) fn skip_read<R: Read>(reader: &mut R, length: usize) -> std::io::Result<()> {
+    let skipped = std::io::copy(&mut reader.take(length as u64), &mut std::io::sink())?;
+    if skipped < length as u64 {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
 
-        // Add the IPTC block
-        binary.extend_from_slice(&iptc_block);
+// Checks the marker of a segment read from a stream, and returns whether it
+// ends the metadata segments: SOS, where the image data starts, or EOI
+fn ends_header(marker: [u8; 2]) -> Result<bool, Box<dyn Error>> {
+    if marker[0] != 0xFF {
+        return Err(format!("Not a valid marker, found: {}", marker[0]).into());
+    }
+    Ok(marker[1] == 0xDA || marker[1] == 0xD9)
+}
 
-        // Pad to even length if needed
-        if binary.len() % 2 != 0 {
-            binary.push(0x00);
-        }
+// Length of a segment's payload, from the length field that follows its
+// marker
+fn segment_length(marker: [u8; 2], length: [u8; 2]) -> Result<usize, Box<dyn Error>> {
+    let length = u16::from_be_bytes(length) as usize;
+    if length < 2 {
+        return Err(format!("Invalid length for segment {:#04X}", marker[1]).into());
+    }
+    Ok(length - 2)
+}
 
-        Ok(binary)
+fn push_segment(buffer: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    buffer.extend_from_slice(&[0xFF, marker]);
+    // Length includes the two length bytes
+    buffer.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    buffer.extend_from_slice(payload);
+}
+
+/// Copies a JPEG, replacing the first `marker` segment whose payload starts
+/// with `signature` by one holding `payload` and dropping any others. When
+/// there is no such segment, the new one goes after APP0 and APP1. A `None`
+/// payload removes the segment.
+fn replace_segment(
+    buffer: &[u8],
+    marker: u8,
+    signature: &[u8],
+    payload: Option<&[u8]>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut new_buffer = Vec::new();
+
+    // Copy the initial JPEG marker (SOI)
+    if buffer.len() < 2 || buffer[0] != 0xFF || buffer[1] != 0xD8 {
+        return Err("Not a valid JPEG file".into());
     }
+    if payload.is_some_and(|payload| payload.len() > u16::MAX as usize - 2) {
+        return Err("Metadata is too large for a JPEG segment".into());
+    }
+    new_buffer.extend_from_slice(&buffer[0..2]);
+    let mut offset = 2;
+
+    // Once written, any other matching segment is dropped
+    let mut written = payload.is_none();
+
+    // Copy segments until we find SOS
+    while offset + 1 < buffer.len() {
+        // Every JPEG segment must start with 0xFF
+        if buffer[offset] != 0xFF {
+            offset += 1;
+            continue;
+        }
+
+        let segment_marker = buffer[offset + 1];
+
+        // Skip empty markers
+        if segment_marker == 0xFF {
+            offset += 1;
+            continue;
+        }
+
+        // For markers without length field
+        if segment_marker == 0x00
+            || segment_marker == 0x01
+            || (0xD0..=0xD7).contains(&segment_marker)
+        {
+            new_buffer.extend_from_slice(&buffer[offset..offset + 2]);
+            offset += 2;
+            continue;
+        }
+
+        // End of image marker
+        if segment_marker == 0xD9 {
+            new_buffer.extend_from_slice(&buffer[offset..offset + 2]);
+            break;
+        }
+
+        // Start of scan marker - copy the rest of the file
+        if segment_marker == 0xDA {
+            if let Some(payload) = payload.filter(|_| !written) {
+                push_segment(&mut new_buffer, marker, payload);
+            }
 
-    fn get_record_dataset(tag: &IPTCTag) -> Option<(u8, u8)> {
-        let tags_map = TagsMap::new();
-        tags_map.get_record_dataset(tag)
+            // Copy SOS marker and all remaining data
+            new_buffer.extend_from_slice(&buffer[offset..]);
+            break;
+        }
+
+        // Check if we can read the length
+        if offset + 3 >= buffer.len() {
+            new_buffer.extend_from_slice(&buffer[offset..]);
+            break;
+        }
+
+        let length = buffer.read_u16be(offset + 2) as usize;
+
+        // Validate segment length
+        if length < 2 || offset + 2 + length > buffer.len() {
+            new_buffer.extend_from_slice(&buffer[offset..]);
+            break;
+        }
+
+        let segment = &buffer[offset..offset + 2 + length];
+        if segment_marker == marker && segment[4..].starts_with(signature) {
+            // Replace the segment with our new data, or drop it
+            if let Some(payload) = payload.filter(|_| !written) {
+                push_segment(&mut new_buffer, marker, payload);
+            }
+            written = true;
+        } else {
+            // Insert our segment after APP0/APP1 but before other segments
+            if !written
+                && segment_marker > APP1
+                && let Some(payload) = payload
+            {
+                push_segment(&mut new_buffer, marker, payload);
+                written = true;
+            }
+
+            // Copy the marker and its data
+            new_buffer.extend_from_slice(segment);
+        }
+        offset += 2 + length;
     }
+
+    Ok(new_buffer)
 }
@@ -45,23 +45,150 @@
 //! }
 //! ```
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "std")]
+mod atomic;
+#[cfg(feature = "batch")]
+pub mod batch;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "std")]
+mod diff;
+#[cfg(feature = "std")]
+pub use diff::{Change, Diff};
+#[cfg(feature = "json")]
+mod exiftool;
+#[cfg(feature = "std")]
+mod filename;
+mod format;
+#[cfg(feature = "std")]
+pub use filename::{Collision, FilenameFormatter, Rename};
+#[cfg(feature = "std")]
+use format::SNIFF_LENGTH;
+pub use format::{ImageFormat, guess_format};
+pub mod iim;
+pub use iim::Dataset;
+#[cfg(feature = "std")]
+mod iptc_ref;
+#[cfg(feature = "std")]
+pub use iptc_ref::{Datasets, IptcRef};
+#[cfg(feature = "std")]
 mod jpeg;
+#[cfg(feature = "std")]
+mod merge;
+#[cfg(feature = "std")]
 use jpeg::JPEGReader;
+#[cfg(feature = "std")]
+pub use merge::MergeStrategy;
+#[cfg(feature = "std")]
 mod tiff;
+#[cfg(feature = "std")]
 use tiff::TIFFReader;
+#[cfg(feature = "std")]
 mod reader;
+#[cfg(feature = "std")]
+mod redact;
+#[cfg(feature = "std")]
+pub use redact::{Redaction, RedactionPolicy, RedactionReport, redact};
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+use reader::{insert_position, merge_datasets};
 mod tags;
-use image::ImageFormat;
+#[cfg(feature = "template")]
+mod template;
+#[cfg(feature = "std")]
+mod transfer;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
+#[cfg(feature = "std")]
 use std::error::Error;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
 use std::path::Path;
-pub use tags::IPTCTag;
+pub use tags::{IPTCTag, ParseTagError, TagInfo, ValueType};
+#[cfg(feature = "template")]
+pub use template::{Template, TemplateContext, TemplateField, TemplateMode};
+#[cfg(feature = "std")]
+pub use transfer::{MetadataGroup, Selection, TransferMode, TransferOptions, transfer};
+#[cfg(feature = "std")]
+pub use validate::{ValidationError, Violation, WritePolicy};
+
+/// Options for writing IPTC metadata.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// What to do with values that break the IIM length and format constraints.
+    pub policy: WritePolicy,
+    /// Keep the file's permissions when writing to a file. On by default.
+    pub preserve_permissions: bool,
+    /// Keep the file's modification time when writing to a file. On by
+    /// default.
+    pub preserve_modified: bool,
+    /// Keep the original file next to the new one, with `.bak` added to its
+    /// name. Any older backup is replaced.
+    pub backup: bool,
+    /// Zero bytes to reserve after the IIM datasets, so that later changes
+    /// can be written in place with `write_to_file_in_place`.
+    pub padding: usize,
+}
 
-#[derive(Default)]
+/// How `IPTC::write_to_file_in_place` updated a file.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WriteMethod {
+    /// Only the bytes of the APP13 segment were overwritten.
+    InPlace,
+    /// The metadata didn't fit, so the whole file was rewritten.
+    Rewrite,
+}
+
+#[cfg(feature = "std")]
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            policy: WritePolicy::default(),
+            preserve_permissions: true,
+            preserve_modified: true,
+            backup: false,
+            padding: 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
 pub struct IPTC {
     pub data: HashMap<IPTCTag, Vec<String>>,
+    /// Every dataset read from the file or set by number, in file order.
+    /// Datasets that no `IPTCTag` covers are written back from here unchanged.
+    datasets: Vec<Dataset>,
 }
 
+/// Two instances are equal when `diff` finds no changes between them: the same
+/// values for every tag, and the same bytes for every dataset no tag covers,
+/// wherever they were read from.
+#[cfg(feature = "std")]
+impl PartialEq for IPTC {
+    fn eq(&self, other: &Self) -> bool {
+        self.diff(other).is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for IPTC {}
+
+#[cfg(feature = "std")]
 impl IPTC {
     /// Creates an empty IPTC metadata collection.
     pub fn new() -> Self {
@@ -74,7 +201,7 @@ impl IPTC {
 
     pub fn get(&self, tag: IPTCTag) -> String {
         let returned_tag = self.data.get(&tag);
-        if returned_tag == None {
+        if returned_tag.is_none() {
             return String::new();
         }
         returned_tag.unwrap().join(", ")
@@ -92,51 +219,224 @@ impl IPTC {
         }
     }
 
+    /// Returns every dataset in the order it will be written, which is file
+    /// order for the datasets that were read from a file.
+    pub fn datasets(&self) -> Vec<Dataset> {
+        merge_datasets(&self.data, &self.datasets)
+    }
+
+    /// Returns the raw values of a dataset by record and dataset number.
+    pub fn get_dataset(&self, record: u8, dataset: u8) -> Vec<Vec<u8>> {
+        self.datasets()
+            .into_iter()
+            .filter(|d| d.record == record && d.dataset == dataset)
+            .map(|d| d.value)
+            .collect()
+    }
+
+    /// Sets a dataset by record and dataset number, replacing its values.
+    ///
+    /// Datasets that `IPTCTag` doesn't cover are written back as is; the
+    /// ones it does cover are also updated in `data`.
+    pub fn set_dataset(&mut self, record: u8, dataset: u8, value: &[u8]) {
+        // Replace the dataset where it was, if it was there at all
+        let position = self
+            .datasets
+            .iter()
+            .position(|d| d.record == record && d.dataset == dataset);
+        self.remove_dataset(record, dataset);
+        let position = position.unwrap_or_else(|| insert_position(&self.datasets, record, dataset));
+
+        let new_dataset = Dataset::new(record, dataset, value);
+        if let Some(tag) = new_dataset.tag() {
+            self.data.insert(tag, vec![new_dataset.decoded()]);
+        }
+        self.datasets.insert(position, new_dataset);
+    }
+
+    /// Adds a value to a dataset, after any values it already has.
+    pub fn add_dataset(&mut self, record: u8, dataset: u8, value: &[u8]) {
+        let new_dataset = Dataset::new(record, dataset, value);
+        if let Some(tag) = new_dataset.tag() {
+            self.data
+                .entry(tag)
+                .or_default()
+                .push(new_dataset.decoded());
+        }
+        let position = insert_position(&self.datasets, record, dataset);
+        self.datasets.insert(position, new_dataset);
+    }
+
+    /// Removes every value of a dataset by record and dataset number.
+    pub fn remove_dataset(&mut self, record: u8, dataset: u8) {
+        if let Some(tag) = Dataset::new(record, dataset, &[]).tag() {
+            self.data.remove(&tag);
+        }
+        self.datasets
+            .retain(|d| d.record != record || d.dataset != dataset);
+    }
+
+    /// Checks every value against the IIM length and format constraints of
+    /// its tag, and returns all the violations found.
+    pub fn validate(&self) -> Vec<Violation> {
+        validate::validate(&self.data, &self.datasets())
+    }
+
     /// Produces a new JPEG image buffer augmented with IPTC metadata.
     pub fn write_to_buffer(&self, image_buffer: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
-        let format = image::guess_format(image_buffer)?;
+        self.write_to_buffer_with(image_buffer, &WriteOptions::default())
+    }
+
+    /// Produces a new JPEG image buffer augmented with IPTC metadata, written
+    /// according to `options`.
+    pub fn write_to_buffer_with(
+        &self,
+        image_buffer: &[u8],
+        options: &WriteOptions,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let format = guess_format(image_buffer)?;
 
         if format != ImageFormat::Jpeg {
             return Err("Writing IPTC data is only supported for JPEG files".into());
         }
 
-        JPEGReader::write_iptc(image_buffer, &self.data)
+        let mut datasets = self.datasets();
+        validate::apply_policy(options.policy, &self.data, &mut datasets)?;
+
+        JPEGReader::write_iptc(image_buffer, &datasets, options.padding)
+    }
+
+    /// Copies a JPEG image from `reader` to `writer` with this IPTC metadata,
+    /// holding only the metadata segments in memory, whatever the size of
+    /// the image.
+    pub fn write_to_writer<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Box<dyn Error>> {
+        self.write_to_writer_with(reader, writer, &WriteOptions::default())
+    }
+
+    /// Copies a JPEG image from `reader` to `writer` with this IPTC metadata,
+    /// written according to `options`.
+    pub fn write_to_writer_with<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+        options: &WriteOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut datasets = self.datasets();
+        validate::apply_policy(options.policy, &self.data, &mut datasets)?;
+
+        JPEGReader::write_iptc_to(reader, writer, &datasets, options.padding)
     }
 
     /// Writes IPTC metadata to a JPEG file.
+    ///
+    /// The file is replaced in one step, through a temporary file in the same
+    /// directory, so it is never left half-written if the process dies.
     pub fn write_to_file(&self, image_path: &Path) -> Result<(), Box<dyn Error>> {
+        self.write_to_file_with(image_path, &WriteOptions::default())
+    }
+
+    /// Writes IPTC metadata to a JPEG file, according to `options`.
+    pub fn write_to_file_with(
+        &self,
+        image_path: &Path,
+        options: &WriteOptions,
+    ) -> Result<(), Box<dyn Error>> {
         let buffer = std::fs::read(image_path)?;
-        let new_buffer = self.write_to_buffer(&buffer)?;
+        let new_buffer = self.write_to_buffer_with(&buffer, options)?;
+
+        atomic::replace_file(image_path, &new_buffer, options)
+    }
+
+    /// Writes IPTC metadata to a JPEG file by overwriting its IPTC resource
+    /// block where it is, without touching the rest of the file. This needs
+    /// the new datasets to fit in the old block, with the help of any padding
+    /// it was written with; otherwise the file is rewritten like
+    /// `write_to_file_with` does, and with `padding` reserved for next time.
+    ///
+    /// With `backup` set the file is always rewritten, since keeping a backup
+    /// means copying it anyway.
+    pub fn write_to_file_in_place(
+        &self,
+        image_path: &Path,
+        options: &WriteOptions,
+    ) -> Result<WriteMethod, Box<dyn Error>> {
+        if !options.backup {
+            let mut datasets = self.datasets();
+            validate::apply_policy(options.policy, &self.data, &mut datasets)?;
 
-        std::fs::write(image_path, new_buffer)?;
-        Ok(())
+            let mut file = File::options().read(true).write(true).open(image_path)?;
+            let modified = file.metadata()?.modified()?;
+            if JPEGReader::update_iptc_in_place(&mut file, &datasets)? {
+                if options.preserve_modified {
+                    file.set_modified(modified)?;
+                }
+                file.sync_all()?;
+                return Ok(WriteMethod::InPlace);
+            }
+        }
+
+        self.write_to_file_with(image_path, options)?;
+        Ok(WriteMethod::Rewrite)
     }
 
     /// Reads IPTC metadata from a buffer containing a JPEG or TIFF image.
+    /// Other images recognized by `guess_format` read as empty.
     pub fn read_from_buffer(image_buffer: &[u8]) -> Result<Self, Box<dyn Error>> {
-        let format = image::guess_format(image_buffer)?;
+        let format = guess_format(image_buffer)?;
 
-        let mut string_data = HashMap::new();
+        let mut iptc = IPTC::new();
 
         // Check if the file is a JPEG
         if format == ImageFormat::Jpeg {
-            string_data = JPEGReader::read_iptc(image_buffer)?;
+            (iptc.data, iptc.datasets) = JPEGReader::read_iptc(image_buffer)?;
         } else if format == ImageFormat::Tiff {
-            println!("TIFF file detected, not all tags are supported");
-            string_data = TIFFReader::read_iptc(image_buffer)?;
-        } else {
-            println!("Unsupported file, only JPEG & Tiff files are supported");
+            let string_data = TIFFReader::read_iptc(image_buffer)?;
+
+            // Convert String to Vec<String>
+            iptc.data = string_data.into_iter().map(|(k, v)| (k, vec![v])).collect();
         }
 
-        // Convert String to Vec<String>
-        let data = string_data.into_iter().map(|(k, v)| (k, vec![v])).collect();
+        Ok(iptc)
+    }
+
+    /// Reads IPTC metadata from a stream holding a JPEG or TIFF image,
+    /// reading only as much of it as the metadata needs: the segment headers
+    /// up to the image data for JPEG, and the IFD entries for TIFF.
+    ///
+    /// The stream is read from its start. Other images recognized by
+    /// `guess_format` read as empty.
+    pub fn read_from_reader<R: Read + Seek>(reader: &mut R) -> Result<Self, Box<dyn Error>> {
+        let mut head = Vec::with_capacity(SNIFF_LENGTH);
+        reader.seek(SeekFrom::Start(0))?;
+        reader
+            .by_ref()
+            .take(SNIFF_LENGTH as u64)
+            .read_to_end(&mut head)?;
+        reader.seek(SeekFrom::Start(0))?;
+        let format = guess_format(&head)?;
 
-        Ok(IPTC { data })
+        let mut iptc = IPTC::new();
+
+        if format == ImageFormat::Jpeg {
+            (iptc.data, iptc.datasets) = JPEGReader::read_iptc_from(reader)?;
+        } else if format == ImageFormat::Tiff {
+            let string_data = TIFFReader::read_iptc_from(reader)?;
+
+            // Convert String to Vec<String>
+            iptc.data = string_data.into_iter().map(|(k, v)| (k, vec![v])).collect();
+        }
+
+        Ok(iptc)
     }
 
-    /// Reads IPTC metadata from a JPEG or TIFF file.
+    /// Reads IPTC metadata from a JPEG or TIFF file, without reading the
+    /// image data.
     pub fn read_from_path(image_path: &Path) -> Result<Self, Box<dyn Error>> {
-        let buffer = std::fs::read(image_path)?;
-        Self::read_from_buffer(&buffer)
+        let mut reader = BufReader::new(File::open(image_path)?);
+        Self::read_from_reader(&mut reader)
     }
 }
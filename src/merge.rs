@@ -0,0 +1,80 @@
+use crate::IPTC;
+use crate::iim::Dataset;
+use crate::reader::contains_value;
+use crate::tags::{IPTCTag, TagInfo};
+use std::collections::{BTreeSet, HashMap};
+
+/// How `IPTC::merge` combines the values of a tag that both sides have.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// Keep the existing values, only taking tags that are missing.
+    #[default]
+    KeepExisting,
+    /// Take the other side's values.
+    Overwrite,
+    /// Add the other side's values that aren't there yet, ignoring case.
+    /// Tags that aren't repeatable keep their existing value.
+    Union,
+    /// A strategy for each tag. Tags that aren't listed, datasets that no tag
+    /// covers and nested `PerTag` strategies fall back to `KeepExisting`.
+    PerTag(HashMap<IPTCTag, MergeStrategy>),
+}
+
+impl IPTC {
+    /// Merges the tags of `other` into these ones, according to `strategy`.
+    ///
+    /// Tags that `other` doesn't have are always left as they are. Values are
+    /// copied byte for byte, so binary values survive the merge.
+    pub fn merge(&mut self, other: &IPTC, strategy: &MergeStrategy) {
+        let keys: BTreeSet<(u8, u8)> = other
+            .datasets()
+            .iter()
+            .map(|d| (d.record, d.dataset))
+            .collect();
+
+        for (record, dataset) in keys {
+            let info = TagInfo::find(record, dataset);
+            let strategy = match (strategy, info) {
+                (MergeStrategy::PerTag(strategies), Some(info)) => strategies
+                    .get(&info.tag)
+                    .unwrap_or(&MergeStrategy::KeepExisting),
+                (strategy, _) => strategy,
+            };
+
+            let existing = self.get_dataset(record, dataset);
+            let values = other.get_dataset(record, dataset);
+            match strategy {
+                MergeStrategy::Overwrite => {
+                    self.remove_dataset(record, dataset);
+                    for value in &values {
+                        self.add_dataset(record, dataset, value);
+                    }
+                }
+                MergeStrategy::Union if info.is_none_or(|info| info.repeatable) => {
+                    let mut decoded: Vec<String> = existing
+                        .iter()
+                        .map(|v| decode(record, dataset, v))
+                        .collect();
+                    for value in &values {
+                        let value_decoded = decode(record, dataset, value);
+                        if !contains_value(&decoded, &value_decoded) {
+                            self.add_dataset(record, dataset, value);
+                            decoded.push(value_decoded);
+                        }
+                    }
+                }
+                _ => {
+                    if existing.is_empty() {
+                        for value in &values {
+                            self.add_dataset(record, dataset, value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn decode(record: u8, dataset: u8, value: &[u8]) -> String {
+    Dataset::new(record, dataset, value).decoded()
+}
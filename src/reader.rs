@@ -1,183 +1,238 @@
+use crate::iim::{Dataset, IPTC_RESOURCE_ID, block_refs, dataset_refs, decode_value, irb_blocks};
 use crate::tags;
-use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
 use tags::IPTCTag;
-use tags::{NULL_BLOCK, TagsMap};
+use tags::{TagInfo, ValueType};
 
-const FIELD_DELIMITER: u8 = 0x1c;
+/// Decoded tags, plus every dataset in file order.
+pub(crate) type IIMData = (HashMap<IPTCTag, Vec<String>>, Vec<Dataset>);
 
-pub trait ReadUtils {
-    fn read_u16be(&self, offset: usize) -> u16;
-    fn read_i16be(&self, offset: usize) -> i16;
-}
+/// Decodes the IIM datasets of a Photoshop IRB block.
+///
+/// Known tags are decoded into the returned map. Every dataset, known or not,
+/// is also returned in file order so that it can be written back untouched.
+pub(crate) fn read_iptc_data(
+    buffer: &[u8],
+    start: usize,
+    length: usize,
+) -> Result<IIMData, Box<dyn Error>> {
+    let mut decoder = TagDecoder::default();
+    let mut datasets: Vec<Dataset> = Vec::new();
 
-impl ReadUtils for [u8] {
-    fn read_u16be(&self, offset: usize) -> u16 {
-        ((self[offset] as u16) << 8) | (self[offset + 1] as u16)
+    if buffer.get(start..start + 13).ok_or("Invalid slice")? != b"Photoshop 3.0" {
+        return Err("Not valid Photoshop data".into());
     }
 
-    fn read_i16be(&self, offset: usize) -> i16 {
-        ((self[offset] as i16) << 8) | (self[offset + 1] as i16)
+    for block in block_refs(irb_blocks(buffer, start, length)) {
+        let block = block?;
+        if block.resource_id != IPTC_RESOURCE_ID {
+            continue;
+        }
+        // Dataset offsets are from the start of `buffer`
+        let base = start + 13 + block.offset;
+
+        for dataset in dataset_refs(block.data) {
+            decoder.push(dataset.record, dataset.dataset, dataset.value);
+            datasets.push(Dataset {
+                record: dataset.record,
+                dataset: dataset.dataset,
+                value: dataset.value.to_vec(),
+                offset: Some(base + dataset.offset),
+            });
+        }
     }
-}
 
-#[derive(Debug)]
-struct Block {
-    resource_id: i16,
-    #[allow(unused)]
-    name: String,
-    start_of_block: usize,
-    size_of_block: usize,
+    Ok((decoder.data, datasets))
 }
 
-#[derive(Debug)]
-struct Field {
-    record_number: u8,
-    dataset_number: u8,
-    value: String,
+/// Decodes datasets into tag values the way reading does: a repeatable tag
+/// drops values it already has, ignoring case, and a non-repeatable tag
+/// keeps its last value. Unknown datasets are skipped.
+#[derive(Default)]
+pub(crate) struct TagDecoder {
+    pub data: HashMap<IPTCTag, Vec<String>>,
+    // Case-folded hashes of the values of repeatable tags, to find repeated
+    // ones without comparing every pair
+    seen: HashSet<(IPTCTag, u64)>,
+    state: RandomState,
 }
 
-fn extract_iptc_fields_from_block(buffer: &[u8], start: usize, length: usize) -> Vec<Field> {
-    let mut data: Vec<Field> = Vec::new();
-    let end = std::cmp::min(buffer.len(), start + length);
-    let mut i = start;
-
-    while i < end {
-        if buffer[i] == FIELD_DELIMITER {
-            let value_length = buffer.read_u16be(i + 3) as usize;
-            let record_number = buffer[i + 1];
-            let dataset_number = buffer[i + 2];
-
-            // println!(
-            //     "Field at i: {}, length: {}, record_number: {}, dataset_number: {}",
-            //     i, value_length, record_number, dataset_number
-            // );
-            if i + 5 + value_length <= end {
-                let raw_bytes = &buffer[i + 5..i + 5 + value_length];
-                let value = raw_bytes
-                    .iter()
-                    .map(|b| b.to_string())
-                    .collect::<Vec<_>>()
-                    .join(",");
-                data.push(Field {
-                    record_number,
-                    dataset_number,
-                    value,
-                });
+impl TagDecoder {
+    pub fn push(&mut self, record: u8, dataset: u8, raw: &[u8]) {
+        let Some(info) = TagInfo::find(record, dataset) else {
+            return;
+        };
+        let value = decode_value(info.parse_fn(), raw);
+        let values = self.data.entry(info.tag).or_default();
+        if info.repeatable {
+            // A hash seen before is a repeat, or rarely a collision
+            let hash = folded_hash(&self.state, &value);
+            if self.seen.insert((info.tag, hash)) || !contains_value(values, &value) {
+                values.push(value);
             }
-            i += 5 + value_length;
         } else {
-            i += 1;
+            *values = vec![value];
         }
     }
-    data
 }
 
-pub(crate) fn read_iptc_data(
-    buffer: &[u8],
-    start: usize,
-    length: usize,
-) -> Result<HashMap<IPTCTag, String>, Box<dyn Error>> {
-    let mut data: HashMap<IPTCTag, String> = HashMap::new();
-    let tags_map = TagsMap::new();
-
-    if buffer.get(start..start + 13).ok_or("Invalid slice")? != b"Photoshop 3.0" {
-        return Err("Not valid Photoshop data".into());
+// Hashes `value` so that values `contains_value` finds equal hash the same,
+// without allocating a lowercased copy
+fn folded_hash(state: &RandomState, value: &str) -> u64 {
+    let mut hasher = state.build_hasher();
+    let mut buffer = [0u8; 64];
+    let mut length = 0;
+    for c in value.chars().flat_map(char::to_lowercase) {
+        // `to_lowercase` turns a final sigma into 'ς', and any other into 'σ'
+        let c = if c == 'ς' { 'σ' } else { c };
+        if length + c.len_utf8() > buffer.len() {
+            hasher.write(&buffer[..length]);
+            length = 0;
+        }
+        length += c.encode_utf8(&mut buffer[length..]).len();
     }
+    hasher.write(&buffer[..length]);
+    hasher.finish()
+}
 
-    extract_blocks(buffer, start + 13, length)?
-        .iter()
-        .filter(|block| block.resource_id == 1028)
-        .for_each(|block| {
-            // println!("Block: {:?}", block);
-            let fields =
-                extract_iptc_fields_from_block(buffer, block.start_of_block, block.size_of_block);
-            for field in fields {
-                let record_number = field.record_number;
-                let dataset_number = field.dataset_number;
-
-                let tag_key = format!("{}:{}", record_number, dataset_number);
-
-                // println!("Field ID: {}, Field: {:?}", tag_key, field);
-                let (name, repeatable, parse) = tags_map.get(tag_key).unwrap_or(NULL_BLOCK);
-
-                if name != IPTCTag::Null {
-                    let parsed_value = parse(field.value);
-                    if !parsed_value.trim().is_empty() {
-                        if repeatable {
-                            if let Some(existing_value) = data.get_mut(&name) {
-                                if !existing_value
-                                    .to_lowercase()
-                                    .contains(&parsed_value.to_lowercase())
-                                {
-                                    if !existing_value.is_empty() {
-                                        existing_value.push_str(", ");
-                                    }
-                                    existing_value.push_str(&parsed_value);
-                                }
-                            } else {
-                                data.insert(name, parsed_value);
-                            }
-                        } else {
-                            data.insert(name, parsed_value);
-                        }
-                    }
-                }
+/// Whether `values` already has `value`, ignoring case, the way repeated
+/// values of a tag are deduplicated.
+pub(crate) fn contains_value(values: &[String], value: &str) -> bool {
+    // Most values are ASCII, which can be compared without allocating
+    if value.is_ascii() {
+        return values.iter().any(|v| {
+            if v.is_ascii() {
+                v.eq_ignore_ascii_case(value)
+            } else {
+                v.to_lowercase() == value.to_ascii_lowercase()
             }
         });
-
-    Ok(data)
+    }
+    let value = value.to_lowercase();
+    values.iter().any(|v| v.to_lowercase() == value)
 }
 
-fn extract_blocks(
-    buffer: &[u8],
-    start: usize,
-    length: usize,
-) -> Result<Vec<Block>, Box<dyn Error>> {
-    let mut blocks = Vec::new();
-    let end = std::cmp::min(buffer.len(), start + length);
-
-    let mut i = start;
-    while i < end {
-        // Signature: '8BIM'
-        if buffer.get(i..i + 4).ok_or("Invalid slice")? == b"8BIM" {
-            // println!("Found 8BIM at {}", i);
-            // Resource ID is 2 bytes, so use i16BE
-            let resource_id = buffer.read_i16be(i + 4);
-
-            // Name: Pascal string, padded to make the size even
-            if i + 5 >= end {
-                return Err("Invalid offset for name length".into());
+/// Lays out the datasets to write for `data`.
+///
+/// Datasets read from the file keep their position: unknown ones and those
+/// of unchanged tags are copied verbatim, those of changed tags take their
+/// current values from `data`, or are dropped if the tag has been removed.
+/// Tags that weren't in the file are slotted in by record and dataset number.
+pub(crate) fn merge_datasets(
+    data: &HashMap<IPTCTag, Vec<String>>,
+    source: &[Dataset],
+) -> Vec<Dataset> {
+    let mut merged: Vec<Dataset> = Vec::new();
+    let mut written: HashSet<IPTCTag> = HashSet::new();
+
+    // What reading `source` gives, to tell the tags that changed since
+    let mut decoder = TagDecoder::default();
+    for dataset in source {
+        decoder.push(dataset.record, dataset.dataset, &dataset.value);
+    }
+    let unchanged = |tag: IPTCTag| data.get(&tag) == decoder.data.get(&tag);
+
+    for dataset in source {
+        match TagInfo::find(dataset.record, dataset.dataset) {
+            // Unchanged tags are written back byte for byte, even values
+            // that decode the same, like non UTF-8 ones or repeats in
+            // another case
+            Some(info) if unchanged(info.tag) => {
+                written.insert(info.tag);
+                merged.push(dataset.clone());
             }
-            let name_length = buffer[i + 5] as usize;
+            Some(info) => {
+                if written.insert(info.tag) {
+                    merged.extend(encode_tag(info, data, source));
+                }
+            }
+            None => merged.push(dataset.clone()),
+        }
+    }
 
-            let name = String::from_utf8(buffer[i + 6..i + 6 + name_length].to_vec())?;
+    // Sort new tags by record and dataset numbers
+    let mut new_tags: Vec<_> = data
+        .keys()
+        .filter(|tag| !written.contains(tag))
+        .filter_map(|tag| tag.info())
+        .collect();
+    new_tags.sort_by_key(|info| (info.record, info.dataset));
+
+    for info in new_tags {
+        let position = insert_position(&merged, info.record, info.dataset);
+        merged.splice(position..position, encode_tag(info, data, source));
+    }
 
-            // println!("Reading block size at i: {}", i + 6 + name_length);
+    merged
+}
 
-            if i + 6 + name_length + 2 > end {
-                return Err("Invalid offset for block size".into());
-            }
-            let block_size = buffer.read_u16be(i + 6 + name_length) as usize;
-
-            // println!(
-            //     "i: {}, name: {}, name_length: {}, block_size: {}",
-            //     i, name, name_length, block_size
-            // );
-
-            blocks.push(Block {
-                resource_id,
-                name,
-                start_of_block: i + 6 + name_length + 4,
-                size_of_block: block_size,
-            });
+/// Index after the last dataset that sorts before or with `record:dataset`.
+pub(crate) fn insert_position(datasets: &[Dataset], record: u8, dataset: u8) -> usize {
+    datasets
+        .iter()
+        .rposition(|d| (d.record, d.dataset) <= (record, dataset))
+        .map_or(0, |i| i + 1)
+}
 
-            i += 6 + name_length + 4;
-            i += block_size;
-        } else {
-            // println!("Not 8BIM at {}: {:?}", i, buffer.get(i..i + 4));
-            i += 1;
+fn encode_tag(
+    info: &TagInfo,
+    data: &HashMap<IPTCTag, Vec<String>>,
+    source: &[Dataset],
+) -> Vec<Dataset> {
+    let (tag, record, dataset) = (info.tag, info.record, info.dataset);
+
+    // Skip empty values
+    let Some(values) = data.get(&tag).filter(|values| !values.is_empty()) else {
+        return Vec::new();
+    };
+
+    // Handle repeatable vs non-repeatable fields
+    // Only the first value of a non-repeatable tag is written;
+    // `Violation::NotRepeatable` reports the others
+    let values_to_process = if info.repeatable {
+        values.as_slice()
+    } else {
+        &values[..1]
+    };
+
+    // Keep the original bytes of values that haven't changed, so that binary
+    // and non UTF-8 values survive a round trip. The first dataset with a
+    // value wins.
+    let mut originals: HashMap<String, &Dataset> = HashMap::new();
+    for d in source {
+        if (d.record, d.dataset) == (record, dataset) {
+            originals
+                .entry(decode_value(info.parse_fn(), &d.value))
+                .or_insert(d);
         }
     }
-    Ok(blocks)
+
+    values_to_process
+        .iter()
+        .map(|value| {
+            if let Some(original) = originals.get(value) {
+                return (*original).clone();
+            }
+
+            // Convert value based on tag format
+            let value = if info.value_type == ValueType::Short {
+                // For short values, convert string to u16 and then to bytes
+                let num_val = value.parse::<u16>().unwrap_or(0);
+                num_val.to_be_bytes().to_vec()
+            } else {
+                // For regular strings, just use UTF-8 bytes
+                value.as_bytes().to_vec()
+            };
+
+            Dataset {
+                record,
+                dataset,
+                value,
+                offset: None,
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,448 @@
+use crate::IPTC;
+use crate::iim::{Dataset, IPTC_RESOURCE_ID, encode_datasets};
+use crate::jpeg::JPEGReader;
+use crate::tags::IPTCTag;
+use crate::transfer::{MetadataGroup, Selection};
+use crate::{ImageFormat, guess_format};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use xml::EmitterConfig;
+use xml::ParserConfig;
+use xml::name::OwnedName;
+use xml::reader::XmlEvent;
+use xml::writer::events::XmlEvent as WriterEvent;
+
+/// Which metadata `redact` removes before an image is shared.
+///
+/// Tags select both their IIM dataset and the XMP property the tag registry
+/// maps them to. Groups select all the IIM datasets, all the resource blocks
+/// other than the IIM one, or the whole XMP packet.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RedactionPolicy {
+    /// Removes all IIM, resource block and XMP metadata.
+    RemoveAll,
+    /// Keeps only who made the image and who owns it: the by-line, credit,
+    /// source and copyright tags, the copyright resource blocks, and their
+    /// XMP properties along with `xmpRights`.
+    KeepRightsOnly,
+    /// Removes what shouldn't leave the newsroom: contact details, special
+    /// instructions, previews and thumbnails, and the XMP editing history.
+    Public,
+    /// Keeps only what is selected.
+    Allow(Vec<Selection>),
+    /// Removes what is selected.
+    Deny(Vec<Selection>),
+}
+
+/// One piece of metadata that `redact` removed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Redaction {
+    Dataset(Dataset),
+    /// A resource block, with the length of its data.
+    Resource {
+        id: u16,
+        length: usize,
+    },
+    /// A property of the XMP packet, as `prefix:name`.
+    XmpProperty(String),
+    /// The whole XMP packet, with its length.
+    Xmp {
+        length: usize,
+    },
+}
+
+impl fmt::Display for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Redaction::Dataset(dataset) => match dataset.tag() {
+                Some(tag) => write!(
+                    f,
+                    "IIM {}:{} {}: {:?}",
+                    dataset.record,
+                    dataset.dataset,
+                    tag,
+                    dataset.decoded()
+                ),
+                None => write!(
+                    f,
+                    "IIM {}:{} ({} bytes)",
+                    dataset.record,
+                    dataset.dataset,
+                    dataset.value.len()
+                ),
+            },
+            Redaction::Resource { id, length } => {
+                write!(f, "Resource 0x{:04X} ({} bytes)", id, length)
+            }
+            Redaction::XmpProperty(name) => write!(f, "XMP {}", name),
+            Redaction::Xmp { length } => write!(f, "XMP packet ({} bytes)", length),
+        }
+    }
+}
+
+/// Everything `redact` removed, in file order within each kind of metadata.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RedactionReport {
+    pub removed: Vec<Redaction>,
+}
+
+impl RedactionReport {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+/// One line per removed piece of metadata.
+impl fmt::Display for RedactionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for redaction in &self.removed {
+            writeln!(f, "- {}", redaction)?;
+        }
+        Ok(())
+    }
+}
+
+const RIGHTS_TAGS: &[IPTCTag] = &[
+    IPTCTag::ByLine,
+    IPTCTag::ByLineTitle,
+    IPTCTag::Credit,
+    IPTCTag::Source,
+    IPTCTag::CopyrightNotice,
+];
+
+// Copyright flag and URL
+const RIGHTS_RESOURCES: &[u16] = &[0x040A, 0x040B];
+
+const PRIVATE_TAGS: &[IPTCTag] = &[
+    IPTCTag::SpecialInstructions,
+    IPTCTag::Contact,
+    IPTCTag::ObjectPreviewFileFormat,
+    IPTCTag::ObjectPreviewFileFormatVersion,
+    IPTCTag::ObjectPreviewData,
+];
+
+// Thumbnails, and the copy of the XMP packet Photoshop keeps in the IRB
+const PRIVATE_RESOURCES: &[u16] = &[0x0409, 0x040C, 0x0424];
+
+const PRIVATE_PROPERTIES: &[&str] = &[
+    "Iptc4xmpCore:CreatorContactInfo",
+    "photoshop:DocumentAncestors",
+    "photoshop:History",
+    "xmp:Thumbnails",
+    "xmpMM:History",
+    "xmpMM:DerivedFrom",
+];
+
+// Datasets that say how the others are encoded. They are kept along with
+// any dataset that is kept, and removed with the last one.
+const STRUCTURAL_TAGS: &[IPTCTag] = &[
+    IPTCTag::ModelVersion,
+    IPTCTag::CodedCharacterSet,
+    IPTCTag::RecordVersion,
+];
+
+// The usual prefix of each namespace, so that properties are matched
+// whatever prefix the packet declares
+const NAMESPACES: &[(&str, &str)] = &[
+    ("http://purl.org/dc/elements/1.1/", "dc"),
+    ("http://ns.adobe.com/xap/1.0/", "xmp"),
+    ("http://ns.adobe.com/xap/1.0/mm/", "xmpMM"),
+    ("http://ns.adobe.com/xap/1.0/rights/", "xmpRights"),
+    ("http://ns.adobe.com/photoshop/1.0/", "photoshop"),
+    (
+        "http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/",
+        "Iptc4xmpCore",
+    ),
+    ("http://www.w3.org/1999/02/22-rdf-syntax-ns#", "rdf"),
+];
+
+/// What happens to the XMP packet.
+enum XmpRule {
+    Keep,
+    Remove,
+    Filter,
+}
+
+impl RedactionPolicy {
+    fn keeps_tag(&self, tag: IPTCTag) -> bool {
+        match self {
+            RedactionPolicy::RemoveAll => false,
+            RedactionPolicy::KeepRightsOnly => {
+                RIGHTS_TAGS.contains(&tag) || STRUCTURAL_TAGS.contains(&tag)
+            }
+            RedactionPolicy::Public => !PRIVATE_TAGS.contains(&tag),
+            RedactionPolicy::Allow(selections) => {
+                selections.contains(&Selection::Group(MetadataGroup::Iim))
+                    || selections.contains(&Selection::Tag(tag))
+                    || STRUCTURAL_TAGS.contains(&tag)
+            }
+            RedactionPolicy::Deny(selections) => {
+                !selections.contains(&Selection::Group(MetadataGroup::Iim))
+                    && !selections.contains(&Selection::Tag(tag))
+            }
+        }
+    }
+
+    // Datasets that no tag covers can only be selected through their group
+    fn keeps_unknown_datasets(&self) -> bool {
+        match self {
+            RedactionPolicy::RemoveAll | RedactionPolicy::KeepRightsOnly => false,
+            RedactionPolicy::Public => true,
+            RedactionPolicy::Allow(selections) => {
+                selections.contains(&Selection::Group(MetadataGroup::Iim))
+            }
+            RedactionPolicy::Deny(selections) => {
+                !selections.contains(&Selection::Group(MetadataGroup::Iim))
+            }
+        }
+    }
+
+    fn keeps_dataset(&self, dataset: &Dataset) -> bool {
+        match dataset.tag() {
+            Some(tag) => self.keeps_tag(tag),
+            None => self.keeps_unknown_datasets(),
+        }
+    }
+
+    fn keeps_resource(&self, id: u16) -> bool {
+        let group = Selection::Group(MetadataGroup::Resources);
+        match self {
+            RedactionPolicy::RemoveAll => false,
+            RedactionPolicy::KeepRightsOnly => RIGHTS_RESOURCES.contains(&id),
+            RedactionPolicy::Public => !PRIVATE_RESOURCES.contains(&id),
+            RedactionPolicy::Allow(selections) => selections.contains(&group),
+            RedactionPolicy::Deny(selections) => !selections.contains(&group),
+        }
+    }
+
+    fn xmp_rule(&self) -> XmpRule {
+        let group = Selection::Group(MetadataGroup::Xmp);
+        match self {
+            RedactionPolicy::RemoveAll => XmpRule::Remove,
+            RedactionPolicy::KeepRightsOnly | RedactionPolicy::Public => XmpRule::Filter,
+            RedactionPolicy::Allow(selections) if selections.contains(&group) => XmpRule::Keep,
+            RedactionPolicy::Allow(selections) => {
+                let has_properties = selections.iter().any(|selection| match selection {
+                    Selection::Tag(tag) => tag.info().and_then(|info| info.xmp).is_some(),
+                    Selection::Group(_) => false,
+                });
+                if has_properties {
+                    XmpRule::Filter
+                } else {
+                    XmpRule::Remove
+                }
+            }
+            RedactionPolicy::Deny(selections) if selections.contains(&group) => XmpRule::Remove,
+            RedactionPolicy::Deny(_) => XmpRule::Filter,
+        }
+    }
+
+    fn keeps_property(&self, name: &str) -> bool {
+        let is_tag_property = |tag: &IPTCTag| tag.info().and_then(|info| info.xmp) == Some(name);
+        match self {
+            RedactionPolicy::RemoveAll => false,
+            RedactionPolicy::KeepRightsOnly => {
+                name.starts_with("xmpRights:") || RIGHTS_TAGS.iter().any(is_tag_property)
+            }
+            RedactionPolicy::Public => {
+                !PRIVATE_PROPERTIES.contains(&name) && !PRIVATE_TAGS.iter().any(is_tag_property)
+            }
+            RedactionPolicy::Allow(selections) => selections.iter().any(|s| match s {
+                Selection::Tag(tag) => is_tag_property(tag),
+                Selection::Group(group) => *group == MetadataGroup::Xmp,
+            }),
+            RedactionPolicy::Deny(selections) => !selections.iter().any(|s| match s {
+                Selection::Tag(tag) => is_tag_property(tag),
+                Selection::Group(group) => *group == MetadataGroup::Xmp,
+            }),
+        }
+    }
+}
+
+/// Removes metadata from a JPEG image according to `policy`, and returns the
+/// new image along with a report of everything that was removed.
+///
+/// Only the APP1 XMP and APP13 segments are rewritten, and only when
+/// something is removed from them; the other segments and the compressed
+/// image data are copied byte for byte.
+pub fn redact(
+    buffer: &[u8],
+    policy: &RedactionPolicy,
+) -> Result<(Vec<u8>, RedactionReport), Box<dyn Error>> {
+    if guess_format(buffer)? != ImageFormat::Jpeg {
+        return Err("Redacting metadata is only supported for JPEG files".into());
+    }
+
+    let mut report = RedactionReport::default();
+    let mut resources = JPEGReader::read_resources(buffer)?;
+    let resource_count = resources.len();
+
+    // The IIM datasets, then the other resource blocks
+    let datasets = IPTC::read_from_buffer(buffer)?.datasets();
+    let (mut kept, mut removed): (Vec<_>, Vec<_>) = datasets
+        .into_iter()
+        .partition(|dataset| policy.keeps_dataset(dataset));
+    if !removed.is_empty() && kept.iter().all(is_structural) {
+        removed.append(&mut kept);
+        removed.sort_by_key(|dataset| dataset.offset);
+    }
+    let iim_changed = !removed.is_empty();
+    report
+        .removed
+        .extend(removed.into_iter().map(Redaction::Dataset));
+
+    resources.retain(|resource| {
+        if resource.id == IPTC_RESOURCE_ID || policy.keeps_resource(resource.id) {
+            return true;
+        }
+        report.removed.push(Redaction::Resource {
+            id: resource.id,
+            length: resource.data.len(),
+        });
+        false
+    });
+    if iim_changed {
+        match resources.iter().position(|r| r.id == IPTC_RESOURCE_ID) {
+            Some(i) if kept.is_empty() => {
+                resources.remove(i);
+            }
+            Some(i) => resources[i].data = encode_datasets(&kept)?,
+            None => {}
+        }
+    }
+
+    let mut new_buffer = if iim_changed || resources.len() != resource_count {
+        JPEGReader::write_resources(buffer, &resources)?
+    } else {
+        buffer.to_vec()
+    };
+
+    if let Some(xmp) = JPEGReader::read_xmp(&new_buffer) {
+        let new_xmp = match policy.xmp_rule() {
+            XmpRule::Keep => None,
+            XmpRule::Remove => {
+                report.removed.push(Redaction::Xmp { length: xmp.len() });
+                Some(None)
+            }
+            XmpRule::Filter => {
+                let (filtered, removed) = filter_xmp(&xmp, policy)?;
+                let changed = !removed.is_empty();
+                report
+                    .removed
+                    .extend(removed.into_iter().map(Redaction::XmpProperty));
+                changed.then_some(Some(filtered))
+            }
+        };
+        if let Some(new_xmp) = new_xmp {
+            new_buffer = JPEGReader::write_xmp(&new_buffer, new_xmp.as_deref())?;
+        }
+    }
+
+    Ok((new_buffer, report))
+}
+
+fn is_structural(dataset: &Dataset) -> bool {
+    dataset
+        .tag()
+        .is_some_and(|tag| STRUCTURAL_TAGS.contains(&tag))
+}
+
+fn property_name(name: &OwnedName) -> String {
+    let prefix = NAMESPACES
+        .iter()
+        .find(|(uri, _)| name.namespace.as_deref() == Some(*uri))
+        .map(|(_, prefix)| *prefix)
+        .or(name.prefix.as_deref());
+    match prefix {
+        Some(prefix) => format!("{}:{}", prefix, name.local_name),
+        None => name.local_name.clone(),
+    }
+}
+
+/// Removes the properties `policy` doesn't keep from the top level
+/// `rdf:Description` elements, whether they are written as elements or as
+/// attributes, and returns the new packet and the names of what was removed.
+fn filter_xmp(
+    xmp: &[u8],
+    policy: &RedactionPolicy,
+) -> Result<(Vec<u8>, Vec<String>), Box<dyn Error>> {
+    let reader = ParserConfig::new()
+        .whitespace_to_characters(true)
+        .create_reader(xmp);
+    let mut output = Vec::new();
+    let mut writer = EmitterConfig::new()
+        .write_document_declaration(false)
+        .normalize_empty_elements(false)
+        .autopad_comments(false)
+        .create_writer(&mut output);
+
+    let mut removed = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    // Depth inside a removed property, 0 when not in one
+    let mut skipping = 0;
+
+    for event in reader {
+        let event = event.map_err(|e| format!("Invalid XMP packet: {}", e))?;
+        let in_description = path.len() >= 2
+            && path[path.len() - 1] == "rdf:Description"
+            && path[path.len() - 2] == "rdf:RDF";
+
+        match &event {
+            XmlEvent::StartDocument { .. } | XmlEvent::EndDocument => continue,
+            XmlEvent::StartElement { .. } if skipping > 0 => skipping += 1,
+            XmlEvent::EndElement { .. } if skipping > 0 => skipping -= 1,
+            _ if skipping > 0 => {}
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let element = property_name(name);
+                if in_description && !policy.keeps_property(&element) {
+                    removed.push(element);
+                    skipping = 1;
+                    continue;
+                }
+
+                let is_description =
+                    element == "rdf:Description" && path.last().is_some_and(|p| p == "rdf:RDF");
+                let attributes: Vec<_> = attributes
+                    .iter()
+                    .filter(|attribute| {
+                        let attribute_name = property_name(&attribute.name);
+                        if !is_description
+                            || attribute_name.starts_with("rdf:")
+                            || policy.keeps_property(&attribute_name)
+                        {
+                            return true;
+                        }
+                        removed.push(attribute_name);
+                        false
+                    })
+                    .map(|attribute| attribute.borrow())
+                    .collect();
+
+                writer.write(WriterEvent::StartElement {
+                    name: name.borrow(),
+                    attributes: Cow::Owned(attributes),
+                    namespace: Cow::Borrowed(namespace),
+                })?;
+                path.push(element);
+            }
+            XmlEvent::EndElement { .. } => {
+                path.pop();
+                if let Some(event) = event.as_writer_event() {
+                    writer.write(event)?;
+                }
+            }
+            _ => {
+                if let Some(event) = event.as_writer_event() {
+                    writer.write(event)?;
+                }
+            }
+        }
+    }
+
+    Ok((output, removed))
+}
@@ -0,0 +1,80 @@
+use crate::IPTC;
+use crate::tags::{IPTCTag, TagInfo};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Tags are serialized by their variant name, and deserialized from any name
+// `IPTCTag::from_str` accepts.
+impl Serialize for IPTCTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IPTCTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Values {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Serializes as a map from tag name to value, in record and dataset order.
+/// Repeatable tags are always arrays, other tags are a single string unless
+/// they hold more than one value. Datasets that no `IPTCTag` covers are left
+/// out.
+impl Serialize for IPTC {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tags: Vec<_> = TagInfo::all()
+            .iter()
+            .filter_map(|info| Some((info, self.data.get(&info.tag)?)))
+            .filter(|(_, values)| !values.is_empty())
+            .collect();
+
+        let mut map = serializer.serialize_map(Some(tags.len()))?;
+        for (info, values) in tags {
+            if info.repeatable || values.len() > 1 {
+                map.serialize_entry(&info.tag, values)?;
+            } else {
+                map.serialize_entry(&info.tag, &values[0])?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for IPTC {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IPTCVisitor;
+
+        impl<'de> Visitor<'de> for IPTCVisitor {
+            type Value = IPTC;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map of IPTC tag names to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<IPTC, A::Error> {
+                let mut iptc = IPTC::new();
+                while let Some((tag, values)) = access.next_entry::<IPTCTag, Values>()? {
+                    let values = match values {
+                        Values::One(value) => vec![value],
+                        Values::Many(values) => values,
+                    };
+                    iptc.data.insert(tag, values);
+                }
+                Ok(iptc)
+            }
+        }
+
+        deserializer.deserialize_map(IPTCVisitor)
+    }
+}
@@ -1,4 +1,9 @@
-use std::collections::HashMap;
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
 use strum_macros::Display;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Display)]
@@ -75,152 +80,307 @@ pub enum IPTCTag {
     SizeMode,
 }
 
-pub(crate) type ParseFn = fn(String) -> String;
-
-// name, repeatable, default parse
-pub(crate) type TagBlock = (IPTCTag, bool, ParseFn);
-
-pub(crate) struct TagsMap {
-    map: HashMap<String, TagBlock>,
-    reverse_map: HashMap<IPTCTag, (u8, u8)>,
+/// The kind of value a dataset holds, as defined by the IIM specification.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ValueType {
+    /// Free text.
+    String,
+    /// Numeric characters only.
+    Digits,
+    /// Alphabetic characters only.
+    Alphabetic,
+    /// A date formatted as CCYYMMDD.
+    Date,
+    /// A time formatted as HHMMSS±HHMM.
+    Time,
+    /// A 2 byte big endian unsigned integer.
+    Short,
+    /// Binary data.
+    Binary,
 }
 
-fn default_parse(s: String) -> String {
-    // Convert comma-separated ASCII values to UTF-8 string
-    let bytes: Vec<u8> = s.split(',').map(|b| b.parse::<u8>().unwrap_or(0)).collect();
-    String::from_utf8(bytes).unwrap_or_default()
+/// Everything the IIM specification says about a tag.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TagInfo {
+    pub tag: IPTCTag,
+    pub record: u8,
+    pub dataset: u8,
+    pub repeatable: bool,
+    pub mandatory: bool,
+    /// Minimum length of a value, in bytes.
+    pub min_length: usize,
+    /// Maximum length of a value, in bytes.
+    pub max_length: usize,
+    pub value_type: ValueType,
+    /// Name of the dataset in the IIM specification, e.g. "Caption/Abstract".
+    pub iim_name: &'static str,
+    /// Name of the tag in ExifTool, e.g. "Caption-Abstract".
+    pub exiftool_name: &'static str,
+    /// The equivalent XMP property, e.g. "dc:description".
+    pub xmp: Option<&'static str>,
 }
 
-pub(crate) fn parse_short(s: String) -> String {
-    // Convert bytes to number, big endian
-    let bytes: Vec<u8> = s.split(',').map(|b| b.parse::<u8>().unwrap_or(0)).collect();
+pub(crate) type ParseFn = fn(&[u8]) -> String;
 
-    if bytes.len() != 2 {
-        return "0".to_string();
+fn default_parse(bytes: &[u8]) -> String {
+    // An empty value has always decoded to a single NUL
+    if bytes.is_empty() {
+        return "\0".to_string();
     }
+    core::str::from_utf8(bytes)
+        .map(str::to_owned)
+        .unwrap_or_default()
+}
 
-    let value = ((bytes[0] as u16) << 8) | (bytes[1] as u16);
-    value.to_string()
+fn parse_short(bytes: &[u8]) -> String {
+    // Convert bytes to number, big endian
+    match bytes {
+        [high, low] => u16::from_be_bytes([*high, *low]).to_string(),
+        _ => "0".to_string(),
+    }
 }
 
-const PARSE_FN: ParseFn = default_parse;
-const PARSE_SHORT: ParseFn = parse_short;
+const R: bool = true;
+const NR: bool = false;
+const M: bool = true;
+const O: bool = false;
 
-pub const NULL_BLOCK: TagBlock = (IPTCTag::Null, false, PARSE_FN);
+#[allow(clippy::too_many_arguments)]
+const fn tag_info(
+    tag: IPTCTag,
+    record: u8,
+    dataset: u8,
+    repeatable: bool,
+    mandatory: bool,
+    min_length: usize,
+    max_length: usize,
+    value_type: ValueType,
+    iim_name: &'static str,
+    exiftool_name: &'static str,
+    xmp: Option<&'static str>,
+) -> TagInfo {
+    TagInfo {
+        tag,
+        record,
+        dataset,
+        repeatable,
+        mandatory,
+        min_length,
+        max_length,
+        value_type,
+        iim_name,
+        exiftool_name,
+        xmp,
+    }
+}
 
 // https://exiftool.org/TagNames/IPTC.html
 // In the IPTC standard, tags are identified by a record number and dataset number.
-// These are in the map as a string of "record:dataset" -> tag.
-impl TagsMap {
-    pub(crate) fn new() -> Self {
-        let map: HashMap<String, TagBlock> = [
-            // Record 1 blocks
-            ("1:0", (IPTCTag::ModelVersion, false, PARSE_SHORT)),
-            ("1:5", (IPTCTag::DateSent, false, PARSE_FN)),
-            ("1:80", (IPTCTag::TimeSent, false, PARSE_FN)),
-            ("1:90", (IPTCTag::CodedCharacterSet, false, PARSE_FN)),
-            // Record 2 blocks
-            ("2:0", (IPTCTag::RecordVersion, false, PARSE_SHORT)),
-            ("2:3", (IPTCTag::ObjectTypeReference, false, PARSE_FN)),
-            ("2:4", (IPTCTag::ObjectAttributeReference, false, PARSE_FN)),
-            ("2:5", (IPTCTag::ObjectName, false, PARSE_FN)),
-            ("2:7", (IPTCTag::EditStatus, false, PARSE_FN)),
-            ("2:8", (IPTCTag::EditorialUpdate, false, PARSE_FN)),
-            ("2:10", (IPTCTag::Urgency, false, PARSE_FN)),
-            ("2:12", (IPTCTag::SubjectReference, false, PARSE_FN)),
-            ("2:15", (IPTCTag::Category, false, PARSE_FN)),
-            ("2:20", (IPTCTag::SupplementalCategories, true, PARSE_FN)),
-            ("2:22", (IPTCTag::FixtureId, true, PARSE_FN)),
-            ("2:25", (IPTCTag::Keywords, true, PARSE_FN)),
-            ("2:26", (IPTCTag::ContentLocationCode, true, PARSE_FN)),
-            ("2:27", (IPTCTag::ContentLocationName, true, PARSE_FN)),
-            ("2:30", (IPTCTag::ReleaseDate, false, PARSE_FN)),
-            ("2:35", (IPTCTag::ReleaseTime, false, PARSE_FN)),
-            ("2:37", (IPTCTag::ExpirationDate, false, PARSE_FN)),
-            ("2:38", (IPTCTag::ExpirationTime, false, PARSE_FN)),
-            ("2:40", (IPTCTag::SpecialInstructions, false, PARSE_FN)),
-            ("2:42", (IPTCTag::ActionAdvised, false, PARSE_FN)),
-            ("2:45", (IPTCTag::ReferenceService, true, PARSE_FN)),
-            ("2:47", (IPTCTag::ReferenceDate, true, PARSE_FN)),
-            ("2:50", (IPTCTag::ReferenceNumber, true, PARSE_FN)),
-            ("2:55", (IPTCTag::DateCreated, false, PARSE_FN)),
-            ("2:60", (IPTCTag::TimeCreated, false, PARSE_FN)),
-            ("2:62", (IPTCTag::DigitalDateCreated, false, PARSE_FN)),
-            ("2:63", (IPTCTag::DigitalTimeCreated, false, PARSE_FN)),
-            ("2:65", (IPTCTag::OriginatingProgram, false, PARSE_FN)),
-            ("2:70", (IPTCTag::ProgramVersion, false, PARSE_FN)),
-            ("2:75", (IPTCTag::ObjectCycle, false, PARSE_FN)),
-            ("2:80", (IPTCTag::ByLine, true, PARSE_FN)),
-            ("2:85", (IPTCTag::ByLineTitle, true, PARSE_FN)),
-            ("2:90", (IPTCTag::City, false, PARSE_FN)),
-            ("2:92", (IPTCTag::SubLocation, false, PARSE_FN)),
-            ("2:95", (IPTCTag::ProvinceOrState, false, PARSE_FN)),
-            (
-                "2:100",
-                (IPTCTag::CountryOrPrimaryLocationCode, false, PARSE_FN),
-            ),
-            (
-                "2:101",
-                (IPTCTag::CountryOrPrimaryLocationName, false, PARSE_FN),
-            ),
-            (
-                "2:103",
-                (IPTCTag::OriginalTransmissionReference, false, PARSE_FN),
-            ),
-            ("2:105", (IPTCTag::Headline, false, PARSE_FN)),
-            ("2:110", (IPTCTag::Credit, false, PARSE_FN)),
-            ("2:115", (IPTCTag::Source, false, PARSE_FN)),
-            ("2:116", (IPTCTag::CopyrightNotice, false, PARSE_FN)),
-            ("2:118", (IPTCTag::Contact, false, PARSE_FN)),
-            ("2:120", (IPTCTag::Caption, false, PARSE_FN)),
-            ("2:121", (IPTCTag::LocalCaption, false, PARSE_FN)),
-            ("2:122", (IPTCTag::CaptionWriter, true, PARSE_FN)),
-            ("2:125", (IPTCTag::RasterizedCaption, false, PARSE_FN)),
-            ("2:130", (IPTCTag::ImageType, false, PARSE_FN)),
-            ("2:131", (IPTCTag::ImageOrientation, false, PARSE_FN)),
-            ("2:135", (IPTCTag::LanguageIdentifier, false, PARSE_FN)),
-            ("2:150", (IPTCTag::AudioType, false, PARSE_FN)),
-            ("2:151", (IPTCTag::AudioSamplingRate, false, PARSE_FN)),
-            ("2:152", (IPTCTag::AudioSamplingResolution, false, PARSE_FN)),
-            ("2:153", (IPTCTag::AudioDuration, false, PARSE_FN)),
-            ("2:154", (IPTCTag::AudioOutcue, false, PARSE_FN)),
-            ("2:184", (IPTCTag::JobId, false, PARSE_FN)),
-            ("2:185", (IPTCTag::MasterDocumentId, false, PARSE_FN)),
-            ("2:186", (IPTCTag::ShortDocumentId, false, PARSE_FN)),
-            ("2:187", (IPTCTag::UniqueDocumentId, false, PARSE_FN)),
-            ("2:188", (IPTCTag::OwnerId, false, PARSE_FN)),
-            ("2:200", (IPTCTag::ObjectPreviewFileFormat, false, PARSE_FN)),
-            (
-                "2:201",
-                (IPTCTag::ObjectPreviewFileFormatVersion, false, PARSE_FN),
-            ),
-            ("2:202", (IPTCTag::ObjectPreviewData, false, PARSE_FN)),
-            // Record 7 blocks
-            ("7:10", (IPTCTag::SizeMode, false, PARSE_FN)),
-        ]
-        .into_iter()
-        .map(|(k, v)| (k.to_string(), v))
-        .collect();
-
-        // Create reverse map from tag to (record,dataset)
-        let reverse_map = map
-            .iter()
-            .map(|(k, &(tag, _, _))| {
-                let parts: Vec<&str> = k.split(':').collect();
-                let record = parts[0].parse::<u8>().unwrap();
-                let dataset = parts[1].parse::<u8>().unwrap();
-                (tag, (record, dataset))
-            })
-            .collect();
-
-        TagsMap { map, reverse_map }
+// Lengths and formats are from the IIM specification, version 4.2.
+// tag, record, dataset, repeatable, mandatory, min length, max length, value type,
+// IIM name, ExifTool name, XMP equivalent
+#[rustfmt::skip]
+static TAGS: &[TagInfo] = &[
+    // Record 1 datasets
+    tag_info(IPTCTag::ModelVersion, 1, 0, NR, M, 2, 2, ValueType::Short, "Model Version", "EnvelopeRecordVersion", None),
+    tag_info(IPTCTag::DateSent, 1, 70, NR, M, 8, 8, ValueType::Date, "Date Sent", "DateSent", None),
+    tag_info(IPTCTag::TimeSent, 1, 80, NR, O, 11, 11, ValueType::Time, "Time Sent", "TimeSent", None),
+    tag_info(IPTCTag::CodedCharacterSet, 1, 90, NR, O, 0, 32, ValueType::Binary, "Coded Character Set", "CodedCharacterSet", None),
+    // Record 2 datasets
+    tag_info(IPTCTag::RecordVersion, 2, 0, NR, M, 2, 2, ValueType::Short, "Record Version", "ApplicationRecordVersion", None),
+    tag_info(IPTCTag::ObjectTypeReference, 2, 3, NR, O, 3, 67, ValueType::String, "Object Type Reference", "ObjectTypeReference", None),
+    tag_info(IPTCTag::ObjectAttributeReference, 2, 4, R, O, 4, 68, ValueType::String, "Object Attribute Reference", "ObjectAttributeReference", None),
+    tag_info(IPTCTag::ObjectName, 2, 5, NR, O, 0, 64, ValueType::String, "Object Name", "ObjectName", Some("dc:title")),
+    tag_info(IPTCTag::EditStatus, 2, 7, NR, O, 0, 64, ValueType::String, "Edit Status", "EditStatus", None),
+    tag_info(IPTCTag::EditorialUpdate, 2, 8, NR, O, 2, 2, ValueType::Digits, "Editorial Update", "EditorialUpdate", None),
+    tag_info(IPTCTag::Urgency, 2, 10, NR, O, 1, 1, ValueType::Digits, "Urgency", "Urgency", Some("photoshop:Urgency")),
+    tag_info(IPTCTag::SubjectReference, 2, 12, R, O, 13, 236, ValueType::String, "Subject Reference", "SubjectReference", Some("Iptc4xmpCore:SubjectCode")),
+    tag_info(IPTCTag::Category, 2, 15, NR, O, 0, 3, ValueType::Alphabetic, "Category", "Category", Some("photoshop:Category")),
+    tag_info(IPTCTag::SupplementalCategories, 2, 20, R, O, 0, 32, ValueType::String, "Supplemental Category", "SupplementalCategories", Some("photoshop:SupplementalCategories")),
+    tag_info(IPTCTag::FixtureId, 2, 22, NR, O, 0, 32, ValueType::String, "Fixture Identifier", "FixtureIdentifier", None),
+    tag_info(IPTCTag::Keywords, 2, 25, R, O, 0, 64, ValueType::String, "Keywords", "Keywords", Some("dc:subject")),
+    tag_info(IPTCTag::ContentLocationCode, 2, 26, R, O, 3, 3, ValueType::Alphabetic, "Content Location Code", "ContentLocationCode", None),
+    tag_info(IPTCTag::ContentLocationName, 2, 27, R, O, 0, 64, ValueType::String, "Content Location Name", "ContentLocationName", None),
+    tag_info(IPTCTag::ReleaseDate, 2, 30, NR, O, 8, 8, ValueType::Date, "Release Date", "ReleaseDate", None),
+    tag_info(IPTCTag::ReleaseTime, 2, 35, NR, O, 11, 11, ValueType::Time, "Release Time", "ReleaseTime", None),
+    tag_info(IPTCTag::ExpirationDate, 2, 37, NR, O, 8, 8, ValueType::Date, "Expiration Date", "ExpirationDate", None),
+    tag_info(IPTCTag::ExpirationTime, 2, 38, NR, O, 11, 11, ValueType::Time, "Expiration Time", "ExpirationTime", None),
+    tag_info(IPTCTag::SpecialInstructions, 2, 40, NR, O, 0, 256, ValueType::String, "Special Instructions", "SpecialInstructions", Some("photoshop:Instructions")),
+    tag_info(IPTCTag::ActionAdvised, 2, 42, NR, O, 2, 2, ValueType::Digits, "Action Advised", "ActionAdvised", None),
+    tag_info(IPTCTag::ReferenceService, 2, 45, R, O, 0, 10, ValueType::String, "Reference Service", "ReferenceService", None),
+    tag_info(IPTCTag::ReferenceDate, 2, 47, R, O, 8, 8, ValueType::Date, "Reference Date", "ReferenceDate", None),
+    tag_info(IPTCTag::ReferenceNumber, 2, 50, R, O, 8, 8, ValueType::Digits, "Reference Number", "ReferenceNumber", None),
+    tag_info(IPTCTag::DateCreated, 2, 55, NR, O, 8, 8, ValueType::Date, "Date Created", "DateCreated", Some("photoshop:DateCreated")),
+    tag_info(IPTCTag::TimeCreated, 2, 60, NR, O, 11, 11, ValueType::Time, "Time Created", "TimeCreated", Some("photoshop:DateCreated")),
+    tag_info(IPTCTag::DigitalDateCreated, 2, 62, NR, O, 8, 8, ValueType::Date, "Digital Creation Date", "DigitalCreationDate", Some("xmp:CreateDate")),
+    tag_info(IPTCTag::DigitalTimeCreated, 2, 63, NR, O, 11, 11, ValueType::Time, "Digital Creation Time", "DigitalCreationTime", Some("xmp:CreateDate")),
+    tag_info(IPTCTag::OriginatingProgram, 2, 65, NR, O, 0, 32, ValueType::String, "Originating Program", "OriginatingProgram", Some("xmp:CreatorTool")),
+    tag_info(IPTCTag::ProgramVersion, 2, 70, NR, O, 0, 10, ValueType::String, "Program Version", "ProgramVersion", None),
+    tag_info(IPTCTag::ObjectCycle, 2, 75, NR, O, 1, 1, ValueType::Alphabetic, "Object Cycle", "ObjectCycle", None),
+    tag_info(IPTCTag::ByLine, 2, 80, R, O, 0, 32, ValueType::String, "By-line", "By-line", Some("dc:creator")),
+    tag_info(IPTCTag::ByLineTitle, 2, 85, R, O, 0, 32, ValueType::String, "By-line Title", "By-lineTitle", Some("photoshop:AuthorsPosition")),
+    tag_info(IPTCTag::City, 2, 90, NR, O, 0, 32, ValueType::String, "City", "City", Some("photoshop:City")),
+    tag_info(IPTCTag::SubLocation, 2, 92, NR, O, 0, 32, ValueType::String, "Sub-location", "Sub-location", Some("Iptc4xmpCore:Location")),
+    tag_info(IPTCTag::ProvinceOrState, 2, 95, NR, O, 0, 32, ValueType::String, "Province/State", "Province-State", Some("photoshop:State")),
+    tag_info(IPTCTag::CountryOrPrimaryLocationCode, 2, 100, NR, O, 3, 3, ValueType::Alphabetic, "Country/Primary Location Code", "Country-PrimaryLocationCode", Some("Iptc4xmpCore:CountryCode")),
+    tag_info(IPTCTag::CountryOrPrimaryLocationName, 2, 101, NR, O, 0, 64, ValueType::String, "Country/Primary Location Name", "Country-PrimaryLocationName", Some("photoshop:Country")),
+    tag_info(IPTCTag::OriginalTransmissionReference, 2, 103, NR, O, 0, 32, ValueType::String, "Original Transmission Reference", "OriginalTransmissionReference", Some("photoshop:TransmissionReference")),
+    tag_info(IPTCTag::Headline, 2, 105, NR, O, 0, 256, ValueType::String, "Headline", "Headline", Some("photoshop:Headline")),
+    tag_info(IPTCTag::Credit, 2, 110, NR, O, 0, 32, ValueType::String, "Credit", "Credit", Some("photoshop:Credit")),
+    tag_info(IPTCTag::Source, 2, 115, NR, O, 0, 32, ValueType::String, "Source", "Source", Some("photoshop:Source")),
+    tag_info(IPTCTag::CopyrightNotice, 2, 116, NR, O, 0, 128, ValueType::String, "Copyright Notice", "CopyrightNotice", Some("dc:rights")),
+    tag_info(IPTCTag::Contact, 2, 118, R, O, 0, 128, ValueType::String, "Contact", "Contact", None),
+    tag_info(IPTCTag::Caption, 2, 120, NR, O, 0, 2000, ValueType::String, "Caption/Abstract", "Caption-Abstract", Some("dc:description")),
+    tag_info(IPTCTag::LocalCaption, 2, 121, NR, O, 0, 256, ValueType::String, "Local Caption", "LocalCaption", None),
+    tag_info(IPTCTag::CaptionWriter, 2, 122, R, O, 0, 32, ValueType::String, "Writer/Editor", "Writer-Editor", Some("photoshop:CaptionWriter")),
+    tag_info(IPTCTag::RasterizedCaption, 2, 125, NR, O, 7360, 7360, ValueType::Binary, "Rasterized Caption", "RasterizedCaption", None),
+    tag_info(IPTCTag::ImageType, 2, 130, NR, O, 2, 2, ValueType::String, "Image Type", "ImageType", None),
+    tag_info(IPTCTag::ImageOrientation, 2, 131, NR, O, 1, 1, ValueType::Alphabetic, "Image Orientation", "ImageOrientation", None),
+    tag_info(IPTCTag::LanguageIdentifier, 2, 135, NR, O, 2, 3, ValueType::Alphabetic, "Language Identifier", "LanguageIdentifier", None),
+    tag_info(IPTCTag::AudioType, 2, 150, NR, O, 2, 2, ValueType::String, "Audio Type", "AudioType", None),
+    tag_info(IPTCTag::AudioSamplingRate, 2, 151, NR, O, 6, 6, ValueType::Digits, "Audio Sampling Rate", "AudioSamplingRate", None),
+    tag_info(IPTCTag::AudioSamplingResolution, 2, 152, NR, O, 2, 2, ValueType::Digits, "Audio Sampling Resolution", "AudioSamplingResolution", None),
+    tag_info(IPTCTag::AudioDuration, 2, 153, NR, O, 6, 6, ValueType::Digits, "Audio Duration", "AudioDuration", None),
+    tag_info(IPTCTag::AudioOutcue, 2, 154, NR, O, 0, 64, ValueType::String, "Audio Outcue", "AudioOutcue", None),
+    tag_info(IPTCTag::JobId, 2, 184, NR, O, 0, 64, ValueType::String, "Job Identifier", "JobID", None),
+    tag_info(IPTCTag::MasterDocumentId, 2, 185, NR, O, 0, 256, ValueType::String, "Master Document Identifier", "MasterDocumentID", None),
+    tag_info(IPTCTag::ShortDocumentId, 2, 186, NR, O, 0, 64, ValueType::String, "Short Document Identifier", "ShortDocumentID", None),
+    tag_info(IPTCTag::UniqueDocumentId, 2, 187, NR, O, 0, 128, ValueType::String, "Unique Document Identifier", "UniqueDocumentID", None),
+    tag_info(IPTCTag::OwnerId, 2, 188, NR, O, 0, 128, ValueType::String, "Owner Identifier", "OwnerID", None),
+    tag_info(IPTCTag::ObjectPreviewFileFormat, 2, 200, NR, O, 2, 2, ValueType::Short, "ObjectData Preview File Format", "ObjectPreviewFileFormat", None),
+    tag_info(IPTCTag::ObjectPreviewFileFormatVersion, 2, 201, NR, O, 2, 2, ValueType::Short, "ObjectData Preview File Format Version", "ObjectPreviewFileVersion", None),
+    tag_info(IPTCTag::ObjectPreviewData, 2, 202, NR, O, 0, 256000, ValueType::Binary, "ObjectData Preview Data", "ObjectPreviewData", None),
+    // Record 7 datasets
+    tag_info(IPTCTag::SizeMode, 7, 10, NR, O, 1, 1, ValueType::Binary, "Size Mode", "SizeMode", None),
+];
+
+impl TagInfo {
+    /// Every tag known to this crate, in record and dataset order.
+    pub fn all() -> &'static [TagInfo] {
+        TAGS
+    }
+
+    /// Looks up a tag by its record and dataset numbers.
+    pub fn find(record: u8, dataset: u8) -> Option<&'static TagInfo> {
+        // `TAGS` is sorted by record and dataset number
+        TAGS.binary_search_by_key(&(record, dataset), |info| (info.record, info.dataset))
+            .ok()
+            .map(|index| &TAGS[index])
+    }
+
+    pub(crate) fn parse_fn(&self) -> ParseFn {
+        match self.value_type {
+            ValueType::Short => parse_short,
+            _ => default_parse,
+        }
+    }
+}
+
+impl IPTCTag {
+    /// Returns the registry entry for this tag, or `None` for `IPTCTag::Null`.
+    pub fn info(self) -> Option<&'static TagInfo> {
+        TAGS.iter().find(|info| info.tag == self)
     }
+    /// Iterates over every tag in the registry, in record and dataset order.
+    pub fn iter() -> impl Iterator<Item = IPTCTag> {
+        TAGS.iter().map(|info| info.tag)
+    }
+}
+
+// Labels used by Photoshop's File Info dialog and other common names, on top
+// of the variant, IIM and ExifTool names every tag already answers to.
+static ALIASES: &[(&str, IPTCTag)] = &[
+    ("Title", IPTCTag::ObjectName),
+    ("Document Title", IPTCTag::ObjectName),
+    ("Intellectual Genre", IPTCTag::ObjectAttributeReference),
+    ("IPTC Subject Code", IPTCTag::SubjectReference),
+    ("Subject Code", IPTCTag::SubjectReference),
+    ("Instructions", IPTCTag::SpecialInstructions),
+    ("Creator", IPTCTag::ByLine),
+    ("Author", IPTCTag::ByLine),
+    ("Creator's Job Title", IPTCTag::ByLineTitle),
+    ("Author's Position", IPTCTag::ByLineTitle),
+    ("Location", IPTCTag::SubLocation),
+    ("State", IPTCTag::ProvinceOrState),
+    ("State/Province", IPTCTag::ProvinceOrState),
+    ("ISO Country Code", IPTCTag::CountryOrPrimaryLocationCode),
+    ("Country Code", IPTCTag::CountryOrPrimaryLocationCode),
+    ("Country", IPTCTag::CountryOrPrimaryLocationName),
+    (
+        "Transmission Reference",
+        IPTCTag::OriginalTransmissionReference,
+    ),
+    ("Credit Line", IPTCTag::Credit),
+    ("Copyright", IPTCTag::CopyrightNotice),
+    ("Description", IPTCTag::Caption),
+    ("Description Writer", IPTCTag::CaptionWriter),
+];
 
-    pub(crate) fn get(&self, tag: String) -> Option<TagBlock> {
-        self.map.get(&tag).copied()
+/// Error returned when a name or number pair doesn't match any known tag.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseTagError(String);
+
+impl fmt::Display for ParseTagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown IPTC tag: {}", self.0)
     }
+}
+
+impl Error for ParseTagError {}
+
+// Compare names ignoring case, spaces and punctuation, so that "By-line",
+// "byline" and "ByLine" are all the same name
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl FromStr for IPTCTag {
+    type Err = ParseTagError;
+
+    /// Parses a tag from its variant name ("Caption"), IIM name
+    /// ("Caption/Abstract"), ExifTool name ("IPTC:Caption-Abstract"),
+    /// Photoshop label ("Description") or "record:dataset" ("2:120").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        let name = match name.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("IPTC:") => &name[5..],
+            _ => name,
+        };
+
+        if let Some((record, dataset)) = name.split_once(':')
+            && let (Ok(record), Ok(dataset)) = (record.parse::<u8>(), dataset.parse::<u8>())
+        {
+            return IPTCTag::try_from((record, dataset));
+        }
+
+        let normalized = normalize(name);
+        let known = TAGS.iter().find(|info| {
+            normalize(&info.tag.to_string()) == normalized
+                || normalize(info.iim_name) == normalized
+                || normalize(info.exiftool_name) == normalized
+        });
+        if let Some(info) = known {
+            return Ok(info.tag);
+        }
+
+        ALIASES
+            .iter()
+            .find(|(alias, _)| normalize(alias) == normalized)
+            .map(|(_, tag)| *tag)
+            .ok_or_else(|| ParseTagError(s.to_string()))
+    }
+}
+
+impl TryFrom<(u8, u8)> for IPTCTag {
+    type Error = ParseTagError;
 
-    pub(crate) fn get_record_dataset(&self, tag: &IPTCTag) -> Option<(u8, u8)> {
-        self.reverse_map.get(tag).copied()
+    /// Looks up a tag by its record and dataset numbers.
+    fn try_from((record, dataset): (u8, u8)) -> Result<Self, Self::Error> {
+        TagInfo::find(record, dataset)
+            .map(|info| info.tag)
+            .ok_or_else(|| ParseTagError(format!("{}:{}", record, dataset)))
     }
 }
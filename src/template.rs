@@ -0,0 +1,240 @@
+use crate::IPTC;
+use crate::reader::contains_value;
+use crate::tags::IPTCTag;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When a template field is written.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemplateMode {
+    /// Only set the tag if it has no value yet.
+    #[default]
+    SetIfEmpty,
+    /// Replace the tag's values.
+    Overwrite,
+    /// Add the values that the tag doesn't have yet, ignoring case like
+    /// reading does. Only for repeatable tags, like Keywords.
+    Append,
+}
+
+/// The values a template gives one tag.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TemplateField {
+    pub tag: IPTCTag,
+    pub values: Vec<String>,
+    pub mode: TemplateMode,
+}
+
+/// A set of tag values to apply to many files, such as the copyright, credit
+/// and contact details a photographer adds to every frame of an assignment.
+///
+/// Templates map tag names to a value, a list of values, or a table with
+/// `values` (or `value`) and a `mode`. In TOML:
+///
+/// ```toml
+/// ByLine = "Jane Doe"
+/// CopyrightNotice = { value = "© {year} Jane Doe", mode = "overwrite" }
+/// Keywords = { values = ["assignment", "{City}"], mode = "append" }
+/// ObjectName = "{filename}"
+/// ```
+///
+/// `{year}` is the current year, `{filename}` the name of the file the
+/// template is applied to, and any tag name, like `{DateCreated}`, the value
+/// that tag had before the template was applied.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Template {
+    pub fields: Vec<TemplateField>,
+}
+
+/// Values for the placeholders that don't come from the tags.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TemplateContext {
+    pub year: i32,
+    pub filename: Option<String>,
+}
+
+impl TemplateContext {
+    /// A context for the current year and the file at `path`, if any.
+    pub fn new(path: Option<&Path>) -> Self {
+        TemplateContext {
+            year: current_year(),
+            filename: path
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Values {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<Values> for Vec<String> {
+    fn from(values: Values) -> Self {
+        match values {
+            Values::One(value) => vec![value],
+            Values::Many(values) => values,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawField {
+    Values(Values),
+    Table {
+        #[serde(alias = "value")]
+        values: Values,
+        #[serde(default)]
+        mode: TemplateMode,
+    },
+}
+
+impl Template {
+    /// Parses a template from TOML.
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_raw(toml::from_str(text)?)
+    }
+
+    /// Parses a template from JSON, with the same layout as the TOML one.
+    pub fn from_json(text: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_raw(serde_json::from_str(text)?)
+    }
+
+    /// Loads a template from a `.toml` or `.json` file.
+    pub fn from_path(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("toml") => Self::from_toml(&text),
+            Some(e) if e.eq_ignore_ascii_case("json") => Self::from_json(&text),
+            _ => Err(format!("Unknown template format: {}", path.display()).into()),
+        }
+    }
+
+    fn from_raw(raw: BTreeMap<String, RawField>) -> Result<Self, Box<dyn Error>> {
+        let mut fields = Vec::new();
+        for (name, field) in raw {
+            let tag = name.parse::<IPTCTag>()?;
+            let (values, mode) = match field {
+                RawField::Values(values) => (values.into(), TemplateMode::default()),
+                RawField::Table { values, mode } => (values.into(), mode),
+            };
+
+            let repeatable = tag.info().is_some_and(|info| info.repeatable);
+            if mode == TemplateMode::Append && !repeatable {
+                return Err(format!("{} is not repeatable, it can't be appended to", tag).into());
+            }
+            fields.push(TemplateField { tag, values, mode });
+        }
+
+        // Registry order, whatever order the file had
+        fields.sort_by_key(|field| IPTCTag::iter().position(|tag| tag == field.tag));
+        Ok(Template { fields })
+    }
+
+    /// Applies the template to `iptc`, expanding placeholders from `context`
+    /// and from the values `iptc` had before.
+    pub fn apply(&self, iptc: &mut IPTC, context: &TemplateContext) -> Result<(), Box<dyn Error>> {
+        let before = iptc.data.clone();
+
+        for field in &self.fields {
+            let values = field
+                .values
+                .iter()
+                .map(|value| expand(value, &before, context))
+                .collect::<Result<Vec<_>, _>>()?;
+            let existing = iptc.data.entry(field.tag).or_default();
+
+            match field.mode {
+                TemplateMode::SetIfEmpty if existing.is_empty() => *existing = values,
+                TemplateMode::SetIfEmpty => {}
+                TemplateMode::Overwrite => *existing = values,
+                TemplateMode::Append => {
+                    for value in values {
+                        if !contains_value(existing, &value) {
+                            existing.push(value);
+                        }
+                    }
+                }
+            }
+            if existing.is_empty() {
+                iptc.data.remove(&field.tag);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a JPEG file, applies the template and writes it back.
+    pub fn apply_to_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut iptc = IPTC::read_from_path(path)?;
+        self.apply(&mut iptc, &TemplateContext::new(Some(path)))?;
+        iptc.write_to_file(path)
+    }
+}
+
+fn expand(
+    value: &str,
+    data: &HashMap<IPTCTag, Vec<String>>,
+    context: &TemplateContext,
+) -> Result<String, Box<dyn Error>> {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in {:?}", value))?;
+        let name = &rest[start + 1..start + end];
+
+        match name {
+            "year" => expanded.push_str(&context.year.to_string()),
+            "filename" => expanded.push_str(
+                context
+                    .filename
+                    .as_deref()
+                    .ok_or("{filename} needs the template to be applied to a file")?,
+            ),
+            _ => {
+                let tag = name
+                    .parse::<IPTCTag>()
+                    .map_err(|_| format!("Unknown placeholder {{{}}}", name))?;
+                if let Some(values) = data.get(&tag) {
+                    expanded.push_str(&values.join(", "));
+                }
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+// Gregorian year of the current UTC date
+fn current_year() -> i32 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400) as i64;
+
+    // Days since 0000-03-01, in 400 year eras
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    // Years start in March here, so January and February belong to the next
+    let year = year_of_era + era * 400 + i64::from(month_index >= 10);
+    year as i32
+}
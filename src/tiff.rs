@@ -1,7 +1,7 @@
 use crate::tags;
 use std::collections::HashMap;
 use std::error::Error;
-use std::io::Cursor;
+use std::io::{Cursor, Read, Seek};
 use tags::IPTCTag;
 use tiff::{
     decoder::{Decoder, ifd::Value},
@@ -13,10 +13,33 @@ pub(crate) struct TIFFReader;
 
 impl TIFFReader {
     pub fn read_iptc(buffer: &[u8]) -> Result<HashMap<IPTCTag, String>, Box<dyn Error>> {
-        let cursor = Cursor::new(buffer);
-        let mut decoder = Decoder::new(cursor)?;
+        Self::read_iptc_from(Cursor::new(buffer))
+    }
+
+    /// Reads the tags from a TIFF stream, seeking to the IFD entries and the
+    /// XMP packet without reading the image data.
+    pub fn read_iptc_from<R: Read + Seek>(
+        reader: R,
+    ) -> Result<HashMap<IPTCTag, String>, Box<dyn Error>> {
+        let bytes = Self::read_xmp_from(reader)?.ok_or("No XMP data in TIFF file")?;
+
+        // Parse the XMP data
+        let data = read_xmp_data(&bytes)?;
 
-        let tag_value = decoder.get_tag(Tag::Unknown(700))?;
+        Ok(data)
+    }
+
+    /// Reads the XMP packet from the XMP tag (700), if there is one.
+    pub fn read_xmp(buffer: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        Self::read_xmp_from(Cursor::new(buffer))
+    }
+
+    fn read_xmp_from<R: Read + Seek>(reader: R) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let mut decoder = Decoder::new(reader)?;
+
+        let Some(tag_value) = decoder.find_tag(Tag::Unknown(700))? else {
+            return Ok(None);
+        };
 
         // println!("Tag value: {:?}", tag_value);
 
@@ -36,10 +59,7 @@ impl TIFFReader {
 
         // println!("Bytes: {:?}", bytes);
 
-        // Parse the XMP data
-        let data = read_xmp_data(&bytes)?;
-
-        Ok(data)
+        Ok(Some(bytes))
     }
 }
 
@@ -83,10 +103,10 @@ fn read_xmp_data(data: &[u8]) -> Result<HashMap<IPTCTag, String>, Box<dyn Error>
                 }
             }
             XmlEvent::Characters(data) => {
-                if let Some(tag) = &current_tag {
-                    if !data.trim().is_empty() {
-                        iptc_data.insert(tag.clone(), data);
-                    }
+                if let Some(tag) = &current_tag
+                    && !data.trim().is_empty()
+                {
+                    iptc_data.insert(*tag, data);
                 }
             }
             XmlEvent::EndElement { name } => {
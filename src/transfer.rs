@@ -0,0 +1,199 @@
+use crate::IPTC;
+use crate::iim::{IPTC_RESOURCE_ID, Resource};
+use crate::jpeg::JPEGReader;
+use crate::tags::{IPTCTag, ParseTagError, TagInfo};
+use crate::tiff::TIFFReader;
+use crate::{ImageFormat, guess_format};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::str::FromStr;
+
+/// The kinds of metadata `transfer` copies.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum MetadataGroup {
+    /// The IIM datasets, known tags or not.
+    Iim,
+    /// The Photoshop resource blocks other than the IIM one.
+    Resources,
+    /// The XMP packet, which is copied as a whole.
+    Xmp,
+}
+
+/// A group or a single tag to include in or exclude from a transfer.
+///
+/// Parses from "IIM" (or "IPTC"), "IRB" (or "Resources"), "XMP", or any tag
+/// name `IPTCTag` accepts.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Selection {
+    Group(MetadataGroup),
+    Tag(IPTCTag),
+}
+
+impl FromStr for Selection {
+    type Err = ParseTagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let group = match s.trim().to_ascii_lowercase().as_str() {
+            "iim" | "iptc" => MetadataGroup::Iim,
+            "irb" | "resources" => MetadataGroup::Resources,
+            "xmp" => MetadataGroup::Xmp,
+            _ => return s.parse().map(Selection::Tag),
+        };
+        Ok(Selection::Group(group))
+    }
+}
+
+/// What happens to the target's metadata for what the source doesn't have.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum TransferMode {
+    /// Keep the target's values, unless the source has its own.
+    #[default]
+    Merge,
+    /// Replace everything selected, removing what the source doesn't have.
+    Replace,
+}
+
+/// Which metadata `transfer` copies, and how.
+///
+/// An empty `include` list selects everything, and `exclude` wins over
+/// `include`, so "all but Keywords" is an exclude list of just `Keywords`.
+/// Including a tag selects only that tag of the IIM group.
+#[derive(Debug, Clone, Default)]
+pub struct TransferOptions {
+    pub include: Vec<Selection>,
+    pub exclude: Vec<Selection>,
+    pub mode: TransferMode,
+}
+
+impl TransferOptions {
+    fn includes_group(&self, group: MetadataGroup) -> bool {
+        self.include.is_empty() || self.include.contains(&Selection::Group(group))
+    }
+
+    fn copies_group(&self, group: MetadataGroup) -> bool {
+        let included = self.includes_group(group)
+            || (group == MetadataGroup::Iim
+                && self.include.iter().any(|s| matches!(s, Selection::Tag(_))));
+        included && !self.exclude.contains(&Selection::Group(group))
+    }
+
+    fn copies_tag(&self, tag: IPTCTag) -> bool {
+        self.copies_group(MetadataGroup::Iim)
+            && (self.includes_group(MetadataGroup::Iim)
+                || self.include.contains(&Selection::Tag(tag)))
+            && !self.exclude.contains(&Selection::Tag(tag))
+    }
+
+    // Datasets that no tag covers can only be selected through their group
+    fn copies_unknown_datasets(&self) -> bool {
+        self.copies_group(MetadataGroup::Iim) && self.includes_group(MetadataGroup::Iim)
+    }
+}
+
+/// Copies metadata from a JPEG or TIFF `source` into a JPEG `target`, and
+/// returns the new target image.
+///
+/// IIM datasets are copied byte for byte, and tag by tag: in `Merge` mode the
+/// target keeps the tags the source doesn't have. Resource blocks are matched
+/// by ID. XMP is copied as a whole packet, so merging only keeps the target's
+/// packet when the source has none.
+pub fn transfer(
+    source: &[u8],
+    target: &[u8],
+    options: &TransferOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if guess_format(target)? != ImageFormat::Jpeg {
+        return Err("Writing IPTC data is only supported for JPEG files".into());
+    }
+    let source_is_jpeg = guess_format(source)? == ImageFormat::Jpeg;
+
+    let mut buffer = target.to_vec();
+
+    if options.copies_group(MetadataGroup::Iim) {
+        let source_iptc = IPTC::read_from_buffer(source)?;
+        let mut target_iptc = IPTC::read_from_buffer(target)?;
+        transfer_datasets(&source_iptc, &mut target_iptc, options);
+        buffer = target_iptc.write_to_buffer(&buffer)?;
+    }
+
+    if options.copies_group(MetadataGroup::Resources) {
+        let source_resources = if source_is_jpeg {
+            JPEGReader::read_resources(source)?
+        } else {
+            Vec::new()
+        };
+        let resources = transfer_resources(
+            source_resources,
+            JPEGReader::read_resources(&buffer)?,
+            options.mode,
+        );
+        buffer = JPEGReader::write_resources(&buffer, &resources)?;
+    }
+
+    if options.copies_group(MetadataGroup::Xmp) {
+        let xmp = if source_is_jpeg {
+            JPEGReader::read_xmp(source)
+        } else {
+            TIFFReader::read_xmp(source)?
+        };
+        if xmp.is_some() || options.mode == TransferMode::Replace {
+            buffer = JPEGReader::write_xmp(&buffer, xmp.as_deref())?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+fn transfer_datasets(source: &IPTC, target: &mut IPTC, options: &TransferOptions) {
+    let mut keys: BTreeSet<(u8, u8)> = TagInfo::all()
+        .iter()
+        .filter(|info| options.copies_tag(info.tag))
+        .map(|info| (info.record, info.dataset))
+        .collect();
+    if options.copies_unknown_datasets() {
+        keys.extend(
+            source
+                .datasets()
+                .iter()
+                .chain(target.datasets().iter())
+                .filter(|d| d.tag().is_none())
+                .map(|d| (d.record, d.dataset)),
+        );
+    }
+
+    for (record, dataset) in keys {
+        let values = source.get_dataset(record, dataset);
+        if values.is_empty() && options.mode == TransferMode::Merge {
+            continue;
+        }
+
+        target.remove_dataset(record, dataset);
+        for value in &values {
+            target.add_dataset(record, dataset, value);
+        }
+    }
+}
+
+fn transfer_resources(
+    source: Vec<Resource>,
+    target: Vec<Resource>,
+    mode: TransferMode,
+) -> Vec<Resource> {
+    // The IIM block is handled with the datasets
+    let (target_iptc, mut resources): (Vec<_>, Vec<_>) =
+        target.into_iter().partition(|r| r.id == IPTC_RESOURCE_ID);
+    if mode == TransferMode::Replace {
+        resources.clear();
+    }
+
+    for resource in source.into_iter().filter(|r| r.id != IPTC_RESOURCE_ID) {
+        match resources.iter_mut().find(|r| r.id == resource.id) {
+            Some(existing) => *existing = resource,
+            None => resources.push(resource),
+        }
+    }
+
+    let mut all = target_iptc;
+    all.extend(resources);
+    all
+}
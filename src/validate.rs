@@ -0,0 +1,268 @@
+use crate::iim::Dataset;
+use crate::tags::{IPTCTag, TagInfo, ValueType};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// What to do with values that break the IIM constraints when writing.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum WritePolicy {
+    /// Refuse to write, returning a `ValidationError` listing every violation.
+    Strict,
+    /// Cut values down to their maximum length. Other violations are written
+    /// as they are.
+    Truncate,
+    /// Write every value as it is.
+    #[default]
+    PassThrough,
+}
+
+/// A value that doesn't meet the IIM constraints for its tag.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    TooLong {
+        tag: IPTCTag,
+        length: usize,
+        max_length: usize,
+    },
+    TooShort {
+        tag: IPTCTag,
+        length: usize,
+        min_length: usize,
+    },
+    InvalidFormat {
+        tag: IPTCTag,
+        value: String,
+        expected: ValueType,
+    },
+    NotRepeatable {
+        tag: IPTCTag,
+        count: usize,
+    },
+}
+
+impl Violation {
+    /// The tag whose value breaks the constraint.
+    pub fn tag(&self) -> IPTCTag {
+        match self {
+            Violation::TooLong { tag, .. }
+            | Violation::TooShort { tag, .. }
+            | Violation::InvalidFormat { tag, .. }
+            | Violation::NotRepeatable { tag, .. } => *tag,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::TooLong {
+                tag,
+                length,
+                max_length,
+            } => write!(
+                f,
+                "{} is {} bytes long, the maximum is {}",
+                tag, length, max_length
+            ),
+            Violation::TooShort {
+                tag,
+                length,
+                min_length,
+            } => write!(
+                f,
+                "{} is {} bytes long, the minimum is {}",
+                tag, length, min_length
+            ),
+            Violation::InvalidFormat {
+                tag,
+                value,
+                expected,
+            } => {
+                let expected = match expected {
+                    ValueType::Digits => "digits only",
+                    ValueType::Alphabetic => "letters only",
+                    ValueType::Date => "a CCYYMMDD date",
+                    ValueType::Time => "an HHMMSS±HHMM time",
+                    ValueType::Short => "a number from 0 to 65535",
+                    ValueType::String | ValueType::Binary => "a valid value",
+                };
+                write!(f, "{} {:?} should be {}", tag, value, expected)
+            }
+            Violation::NotRepeatable { tag, count } => {
+                write!(f, "{} is not repeatable but has {} values", tag, count)
+            }
+        }
+    }
+}
+
+/// Error returned by a strict write when values break the IIM constraints.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ValidationError {
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid IPTC data: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Checks the datasets about to be written against the registry.
+///
+/// Lengths are checked on the encoded bytes. `data` is used for what the
+/// encoding hides: extra values of non-repeatable tags, which are dropped,
+/// and numbers that don't fit in a short, which are written as 0.
+pub(crate) fn validate(
+    data: &HashMap<IPTCTag, Vec<String>>,
+    datasets: &[Dataset],
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    for info in TagInfo::all() {
+        let values = data.get(&info.tag).map_or(&[][..], |values| &values[..]);
+        if !info.repeatable && values.len() > 1 {
+            violations.push(Violation::NotRepeatable {
+                tag: info.tag,
+                count: values.len(),
+            });
+        }
+
+        if info.value_type == ValueType::Short {
+            for value in values {
+                if value.parse::<u16>().is_err() {
+                    violations.push(Violation::InvalidFormat {
+                        tag: info.tag,
+                        value: value.clone(),
+                        expected: ValueType::Short,
+                    });
+                }
+            }
+            continue;
+        }
+
+        for dataset in datasets
+            .iter()
+            .filter(|d| d.record == info.record && d.dataset == info.dataset)
+        {
+            check_value(info, &dataset.value, &mut violations);
+        }
+    }
+
+    violations
+}
+
+fn check_value(info: &TagInfo, value: &[u8], violations: &mut Vec<Violation>) {
+    let length = value.len();
+    if length > info.max_length {
+        violations.push(Violation::TooLong {
+            tag: info.tag,
+            length,
+            max_length: info.max_length,
+        });
+    }
+    if length < info.min_length {
+        violations.push(Violation::TooShort {
+            tag: info.tag,
+            length,
+            min_length: info.min_length,
+        });
+    }
+
+    let valid = match info.value_type {
+        ValueType::Digits => value.iter().all(u8::is_ascii_digit),
+        ValueType::Alphabetic => value.iter().all(u8::is_ascii_alphabetic),
+        ValueType::Date => is_date(value),
+        ValueType::Time => is_time(value),
+        ValueType::String | ValueType::Short | ValueType::Binary => true,
+    };
+    if !valid {
+        violations.push(Violation::InvalidFormat {
+            tag: info.tag,
+            value: String::from_utf8_lossy(value).into_owned(),
+            expected: info.value_type,
+        });
+    }
+}
+
+fn number(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    Some(
+        digits
+            .iter()
+            .fold(0, |n, digit| n * 10 + (digit - b'0') as u32),
+    )
+}
+
+// CCYYMMDD, where 00 stands for an unknown month or day
+fn is_date(value: &[u8]) -> bool {
+    if value.len() != 8 || number(&value[..4]).is_none() {
+        return false;
+    }
+    matches!(number(&value[4..6]), Some(0..=12)) && matches!(number(&value[6..8]), Some(0..=31))
+}
+
+// HHMMSS±HHMM
+fn is_time(value: &[u8]) -> bool {
+    if value.len() != 11 || (value[6] != b'+' && value[6] != b'-') {
+        return false;
+    }
+    matches!(number(&value[0..2]), Some(0..=23))
+        && matches!(number(&value[2..4]), Some(0..=59))
+        && matches!(number(&value[4..6]), Some(0..=59))
+        && matches!(number(&value[7..9]), Some(0..=23))
+        && matches!(number(&value[9..11]), Some(0..=59))
+}
+
+/// Applies `policy` to the datasets about to be written.
+pub(crate) fn apply_policy(
+    policy: WritePolicy,
+    data: &HashMap<IPTCTag, Vec<String>>,
+    datasets: &mut [Dataset],
+) -> Result<(), ValidationError> {
+    match policy {
+        WritePolicy::PassThrough => Ok(()),
+        WritePolicy::Strict => {
+            let violations = validate(data, datasets);
+            if violations.is_empty() {
+                Ok(())
+            } else {
+                Err(ValidationError { violations })
+            }
+        }
+        WritePolicy::Truncate => {
+            for dataset in datasets.iter_mut() {
+                let Some(info) = TagInfo::find(dataset.record, dataset.dataset) else {
+                    continue;
+                };
+                if dataset.value.len() > info.max_length {
+                    truncate(&mut dataset.value, info.max_length);
+                    dataset.offset = None;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+// Cut text on a character boundary so it stays valid UTF-8
+fn truncate(value: &mut Vec<u8>, max_length: usize) {
+    let mut end = max_length;
+    if let Ok(text) = std::str::from_utf8(value) {
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+    }
+    value.truncate(end);
+}
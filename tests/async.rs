@@ -0,0 +1,75 @@
+#![cfg(feature = "async")]
+
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+
+#[tokio::test]
+async fn read_from_async_reader() -> Result<(), Box<dyn Error>> {
+    for path in ["tests/smiley.jpg", "tests/DSC00512.jpg"] {
+        let mut file = tokio::fs::File::open(path).await?;
+        let iptc = IPTC::read_from_async_reader(&mut file).await?;
+
+        let expected = IPTC::read_from_path(path.as_ref())?;
+        assert_eq!(iptc, expected);
+        assert_eq!(iptc.datasets(), expected.datasets());
+    }
+
+    // Reaching the TIFF directories would take reading the whole stream
+    let mut tiff = tokio::fs::File::open("tests/DSC3003.tif").await?;
+    assert!(IPTC::read_from_async_reader(&mut tiff).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_to_async_writer() -> Result<(), Box<dyn Error>> {
+    let mut iptc = IPTC::read_from_path("tests/DSC00512.jpg".as_ref())?;
+    iptc.data.insert(IPTCTag::City, vec!["Oslo".to_string()]);
+
+    let file = tokio::fs::File::open("tests/DSC00512.jpg").await?;
+    let mut written = Vec::new();
+    iptc.write_to_async_writer(file, &mut written).await?;
+
+    assert_eq!(
+        written,
+        iptc.write_to_buffer(&fs::read("tests/DSC00512.jpg")?)?
+    );
+    let read_back = IPTC::read_from_async_reader(&mut &written[..]).await?;
+    assert_eq!(read_back.get(IPTCTag::City), "Oslo");
+
+    Ok(())
+}
+
+#[test]
+fn futures_are_send() {
+    fn assert_send<T: Send>(_: T) {}
+    let mut file: &[u8] = &[];
+    assert_send(IPTC::read_from_async_reader(&mut file));
+    assert_send(IPTC::new().write_to_async_writer(&[][..], Vec::new()));
+}
+
+#[tokio::test]
+async fn fill_bytes_before_markers() -> Result<(), Box<dyn Error>> {
+    // DSC00512.jpg with 0xFF fill bytes before its first marker and its APP13
+    // marker
+    let mut jpeg = fs::read("tests/DSC00512.jpg")?;
+    let app13 = jpeg
+        .windows(2)
+        .position(|w| w == [0xFF, 0xED])
+        .ok_or("no APP13 segment")?;
+    jpeg.insert(app13, 0xFF);
+    jpeg.splice(2..2, [0xFF, 0xFF]);
+
+    let mut iptc = IPTC::read_from_async_reader(&mut &jpeg[..]).await?;
+    assert_eq!(iptc.datasets(), IPTC::read_from_buffer(&jpeg)?.datasets());
+
+    iptc.data.insert(IPTCTag::City, vec!["Oslo".to_string()]);
+    let mut written = Vec::new();
+    iptc.write_to_async_writer(&jpeg[..], &mut written).await?;
+    assert_eq!(written, iptc.write_to_buffer(&jpeg)?);
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "batch")]
+
+use std::error::Error;
+use std::fs;
+use std::sync::Mutex;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::batch::Batch;
+
+mod common;
+
+fn archive(name: &str) -> Result<std::path::PathBuf, Box<dyn Error>> {
+    let dir = common::scratch_dir(name, &["smiley.jpg"])?;
+    fs::create_dir(dir.join("2019"))?;
+    fs::copy("tests/DSC00512.jpg", dir.join("2019/DSC00512.JPG"))?;
+    fs::write(dir.join("2019/broken.jpg"), b"not an image")?;
+    fs::write(dir.join("notes.txt"), b"skipped")?;
+    Ok(dir)
+}
+
+#[test]
+fn read_collects_errors_and_progress() -> Result<(), Box<dyn Error>> {
+    let dir = archive("batch-read")?;
+    let seen = Mutex::new(Vec::new());
+
+    let results = Batch::new(dir.to_str().unwrap())?
+        .threads(2)
+        .on_progress(|progress| seen.lock().unwrap().push((progress.done, progress.total)))
+        .read();
+
+    let names: Vec<_> = results
+        .iter()
+        .map(|r| r.path.strip_prefix(&dir).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(
+        names,
+        ["2019/DSC00512.JPG", "2019/broken.jpg", "smiley.jpg"].map(std::path::PathBuf::from)
+    );
+    assert_eq!(
+        results[0].result.as_ref().unwrap().get(IPTCTag::City),
+        "London"
+    );
+    assert!(results[1].result.is_err());
+    assert!(results[2].result.is_ok());
+
+    let mut seen = seen.into_inner().unwrap();
+    seen.sort();
+    assert_eq!(seen, [(1, 3), (2, 3), (3, 3)]);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn apply_writes_changed_files() -> Result<(), Box<dyn Error>> {
+    let dir = archive("batch-apply")?;
+    let pattern = format!("{}/**/*.jpg", dir.display());
+
+    let results = Batch::new(&pattern)?.apply(|_, iptc| {
+        if !iptc.get(IPTCTag::City).is_empty() {
+            return Ok(false);
+        }
+        iptc.set_tag(IPTCTag::City, "Unknown");
+        Ok(true)
+    });
+
+    // The glob is case-sensitive, so only two files match
+    assert_eq!(results.len(), 2);
+    assert!(results[0].result.is_err());
+    assert!(results[1].written);
+    let smiley = IPTC::read_from_path(&dir.join("smiley.jpg"))?;
+    assert_eq!(smiley.get(IPTCTag::City), "Unknown");
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_directories_are_not_followed() -> Result<(), Box<dyn Error>> {
+    let dir = archive("batch-symlink")?;
+    std::os::unix::fs::symlink(&dir, dir.join("2019/loop"))?;
+    std::os::unix::fs::symlink(dir.join("smiley.jpg"), dir.join("linked.jpg"))?;
+
+    let results = Batch::new(dir.to_str().unwrap())?.read();
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().any(|r| r.path.ends_with("linked.jpg")));
+
+    let pattern = format!("{}/**/*.jpg", dir.display());
+    assert_eq!(Batch::new(&pattern)?.paths().len(), 3);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_directories_are_reported() -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = archive("batch-unreadable")?;
+    let locked = dir.join("2019");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+    // Root can read the directory anyway
+    let readable = fs::read_dir(&locked).is_ok();
+
+    let results = Batch::new(dir.to_str().unwrap())?.read();
+    let pattern = format!("{}/*/*.jpg", dir.display());
+    let matched = Batch::new(&pattern)?.read();
+    // A pattern without `**` doesn't search deeper than it can match
+    let pattern = format!("{}/*.jpg", dir.display());
+    let shallow = Batch::new(&pattern)?.read();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+
+    assert_eq!(shallow.len(), 1);
+    assert!(shallow[0].result.is_ok());
+
+    if !readable {
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, locked);
+        assert!(results[0].result.is_err());
+        assert!(results[1].result.is_ok());
+        assert_eq!(matched.len(), 1);
+        assert!(matched[0].result.is_err());
+    }
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "cli")]
+
+use std::error::Error;
+use std::fs;
+use std::process::{Command, Output};
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+
+mod common;
+use common::scratch_dir;
+
+fn iptc(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_iptc"))
+        .args(args)
+        .output()
+        .expect("failed to run iptc")
+}
+
+#[test]
+fn read_prints_text_and_json() -> Result<(), Box<dyn Error>> {
+    let output = iptc(&["read", "tests/smiley.jpg"]);
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout)?;
+    assert!(text.contains("Headline                        : The headline I am"));
+
+    let output = iptc(&["read", "--json", "tests/smiley.jpg"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json[0]["SourceFile"], "tests/smiley.jpg");
+    assert_eq!(json[0]["IPTC:Keywords"], "Yet another keyword");
+
+    Ok(())
+}
+
+#[test]
+fn set_remove_and_strip() -> Result<(), Box<dyn Error>> {
+    let path = scratch_dir("cli-set", &["photo.jpg"])?.join("photo.jpg");
+    let file = path.to_str().ok_or("Non UTF-8 temp dir")?;
+
+    let output = iptc(&[
+        "set",
+        "-t",
+        "Keywords=foo",
+        "-t",
+        "Keywords=bar",
+        "-t",
+        "City=Oslo",
+        file,
+    ]);
+    assert!(output.status.success());
+    let written = IPTC::read_from_path(&path)?;
+    assert_eq!(written.get(IPTCTag::Keywords), "foo, bar");
+    assert_eq!(written.get(IPTCTag::City), "Oslo");
+
+    assert!(iptc(&["remove", "-t", "City", file]).status.success());
+    assert_eq!(IPTC::read_from_path(&path)?.get(IPTCTag::City), "");
+
+    assert!(iptc(&["strip", file]).status.success());
+    assert!(IPTC::read_from_path(&path)?.get_all().is_empty());
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn copy_between_files() -> Result<(), Box<dyn Error>> {
+    let path = scratch_dir("cli-copy", &["photo.jpg"])?.join("photo.jpg");
+    let file = path.to_str().ok_or("Non UTF-8 temp dir")?;
+    assert!(iptc(&["strip", file]).status.success());
+
+    let output = iptc(&["copy", "--from", "tests/smiley.jpg", "--to", file]);
+    assert!(output.status.success());
+    assert_eq!(
+        IPTC::read_from_path(&path)?.get(IPTCTag::Headline),
+        "The headline I am"
+    );
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn exit_codes() {
+    assert_eq!(iptc(&["read", "tests/missing.jpg"]).status.code(), Some(1));
+    // Only JPEG files can be written
+    assert_eq!(iptc(&["strip", "tests/DSC3003.tif"]).status.code(), Some(1));
+    assert_eq!(
+        iptc(&["set", "-t", "NotATag=x", "tests/smiley.jpg"])
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(iptc(&["read"]).status.code(), Some(2));
+}
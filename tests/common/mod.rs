@@ -0,0 +1,77 @@
+//! Fixtures shared by the integration tests and the benchmarks.
+
+// Each test crate uses only some of them
+#![allow(dead_code)]
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+pub const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Encodes IIM datasets given as record, dataset and value.
+pub fn iim(datasets: &[(u8, u8, &[u8])]) -> Vec<u8> {
+    let mut iim = Vec::new();
+    for (record, dataset, value) in datasets {
+        iim.extend_from_slice(&[0x1C, *record, *dataset]);
+        iim.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        iim.extend_from_slice(value);
+    }
+    iim
+}
+
+/// Builds a bare JPEG with an optional XMP packet, and an APP13 segment
+/// holding the given IIM datasets and extra resource blocks.
+pub fn jpeg_with(
+    datasets: &[(u8, u8, &[u8])],
+    resources: &[(u16, &[u8])],
+    xmp: Option<&[u8]>,
+) -> Vec<u8> {
+    jpeg_with_iim(&iim(datasets), resources, xmp)
+}
+
+/// Like `jpeg_with`, for datasets that are already encoded.
+pub fn jpeg_with_iim(iim: &[u8], resources: &[(u16, &[u8])], xmp: Option<&[u8]>) -> Vec<u8> {
+    let mut irb = b"Photoshop 3.0\0".to_vec();
+    for (id, data) in [(0x0404, iim)].iter().chain(resources) {
+        irb.extend_from_slice(b"8BIM");
+        irb.extend_from_slice(&id.to_be_bytes());
+        irb.extend_from_slice(&[0x00, 0x00]);
+        irb.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        irb.extend_from_slice(data);
+        if data.len() % 2 != 0 {
+            irb.push(0x00);
+        }
+    }
+
+    let mut jpeg = vec![0xFF, 0xD8];
+    if let Some(xmp) = xmp {
+        jpeg.extend_from_slice(&[0xFF, 0xE1]);
+        jpeg.extend_from_slice(&((XMP_SIGNATURE.len() + xmp.len()) as u16 + 2).to_be_bytes());
+        jpeg.extend_from_slice(XMP_SIGNATURE);
+        jpeg.extend_from_slice(xmp);
+    }
+    jpeg.extend_from_slice(&[0xFF, 0xED]);
+    jpeg.extend_from_slice(&(irb.len() as u16 + 2).to_be_bytes());
+    jpeg.extend_from_slice(&irb);
+    jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02]);
+    jpeg.extend_from_slice(&[0x00; 16]);
+    jpeg.extend_from_slice(&[0xFF, 0xD9]);
+    jpeg
+}
+
+/// A fresh directory named after the test, holding a copy of the test image
+/// under each of `files`, so that tests which write don't touch the original.
+pub fn scratch_dir(name: &str, files: &[&str]) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("iptc-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    for file in files {
+        fs::copy("tests/smiley.jpg", dir.join(file))?;
+    }
+    Ok(dir)
+}
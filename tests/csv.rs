@@ -0,0 +1,137 @@
+#![cfg(feature = "csv")]
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::csv::{self, CsvOptions};
+
+mod common;
+use common::scratch_dir;
+
+#[test]
+fn export_one_row_per_file() -> Result<(), Box<dyn Error>> {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::Keywords, "rust");
+    iptc.set_tag(IPTCTag::Keywords, "iptc");
+    iptc.set_tag(IPTCTag::City, "Oslo, Norway");
+
+    let options = CsvOptions {
+        separator: "|".to_string(),
+    };
+    let mut output = Vec::new();
+    csv::export(
+        &mut output,
+        [("a.jpg", &iptc), ("b.jpg", &IPTC::new())],
+        &options,
+    )?;
+    let output = String::from_utf8(output)?;
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    let header: Vec<&str> = lines[0].split(',').collect();
+    assert_eq!(header[0], "File");
+    assert_eq!(header.len(), IPTCTag::iter().count() + 1);
+
+    let keywords = header.iter().position(|h| *h == "Keywords").unwrap();
+    let row = &lines[1];
+    assert!(row.starts_with("a.jpg,"));
+    assert!(row.contains("rust|iptc"));
+    assert!(row.contains("\"Oslo, Norway\""));
+    assert_eq!(lines[2].split(',').nth(keywords), Some(""));
+
+    Ok(())
+}
+
+#[test]
+fn import_matches_rows_by_filename() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("csv-import", &["one.jpg", "two.jpg"])?;
+    let options = CsvOptions::default();
+    let input = "File,City,Keywords,Headline\n\
+                 one.jpg,Oslo,rust; iptc,\n\
+                 two.jpg,,,The headline I am\n";
+
+    let planned = csv::plan_import(input.as_bytes(), &dir, &options)?;
+    let applied = csv::import(input.as_bytes(), &dir, &options)?;
+    assert_eq!(planned, applied);
+
+    let one = IPTC::read_from_path(&dir.join("one.jpg"))?;
+    assert_eq!(one.get(IPTCTag::City), "Oslo");
+    assert_eq!(one.get(IPTCTag::Keywords), "rust, iptc");
+    assert_eq!(one.get(IPTCTag::Headline), "");
+
+    // An unchanged cell isn't reported, and tags without a column stay
+    let two = IPTC::read_from_path(&dir.join("two.jpg"))?;
+    assert_eq!(two.get(IPTCTag::Headline), "The headline I am");
+    assert_eq!(two.get(IPTCTag::Urgency), "very!");
+    let changed: Vec<(&Path, IPTCTag)> = applied
+        .iter()
+        .filter(|c| c.file == dir.join("two.jpg"))
+        .map(|c| (c.file.as_path(), c.tag))
+        .collect();
+    assert_eq!(
+        changed,
+        vec![(dir.join("two.jpg").as_path(), IPTCTag::Keywords)]
+    );
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn dry_run_writes_nothing() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("csv-dry-run", &["photo.jpg"])?;
+    let before = fs::read(dir.join("photo.jpg"))?;
+
+    let input = "File,Caption-Abstract\nphoto.jpg,\"A caption; with a semicolon\"\n";
+    let changes = csv::plan_import(input.as_bytes(), &dir, &CsvOptions::default())?;
+
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].tag, IPTCTag::Caption);
+    assert_eq!(changes[0].new, vec!["A caption; with a semicolon"]);
+    assert_eq!(fs::read(dir.join("photo.jpg"))?, before);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn values_with_the_separator_round_trip() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("csv-round-trip", &["photo.jpg"])?;
+    let options = CsvOptions::default();
+    let keywords = vec![
+        "salt; pepper".to_string(),
+        "a;b".to_string(),
+        r"C:\photos\".to_string(),
+        "plain".to_string(),
+    ];
+
+    let mut iptc = IPTC::new();
+    iptc.data.insert(IPTCTag::Keywords, keywords.clone());
+    let mut output = Vec::new();
+    csv::export(&mut output, [("photo.jpg", &iptc)], &options)?;
+
+    csv::import(&output[..], &dir, &options)?;
+    let imported = IPTC::read_from_path(&dir.join("photo.jpg"))?;
+    assert_eq!(imported.data.get(&IPTCTag::Keywords), Some(&keywords));
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn import_rejects_unknown_columns_and_files() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("csv-errors", &["photo.jpg"])?;
+    let options = CsvOptions::default();
+
+    assert!(csv::plan_import("File,NotATag\nphoto.jpg,x\n".as_bytes(), &dir, &options).is_err());
+    assert!(csv::plan_import("City\nOslo\n".as_bytes(), &dir, &options).is_err());
+    let error =
+        csv::plan_import("File,City\nmissing.jpg,Oslo\n".as_bytes(), &dir, &options).unwrap_err();
+    assert!(error.to_string().contains("missing.jpg"));
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
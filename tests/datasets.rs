@@ -0,0 +1,85 @@
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+
+#[test]
+fn datasets_in_file_order() -> Result<(), Box<dyn Error>> {
+    let buffer = fs::read("tests/DSC00512.jpg")?;
+    let iptc = IPTC::read_from_buffer(&buffer)?;
+
+    let datasets = iptc.datasets();
+    let numbers: Vec<_> = datasets.iter().map(|d| (d.record, d.dataset)).collect();
+    assert_eq!(
+        numbers,
+        [
+            (1, 90),
+            (2, 55),
+            (2, 60),
+            (2, 25),
+            (2, 25),
+            (2, 25),
+            (2, 25),
+            (2, 120),
+            (2, 90),
+            (2, 101)
+        ]
+    );
+
+    // Offsets point at the dataset headers in the original buffer
+    for dataset in &datasets {
+        let offset = dataset.offset.ok_or("Missing offset")?;
+        assert_eq!(buffer[offset], 0x1C);
+        assert_eq!(buffer[offset + 1], dataset.record);
+        assert_eq!(buffer[offset + 2], dataset.dataset);
+        assert_eq!(
+            &buffer[offset + 5..offset + 5 + dataset.value.len()],
+            dataset.value
+        );
+    }
+
+    assert_eq!(datasets[1].tag(), Some(IPTCTag::DateCreated));
+    assert_eq!(datasets[1].decoded(), "20190519");
+
+    Ok(())
+}
+
+#[test]
+fn custom_datasets_round_trip() -> Result<(), Box<dyn Error>> {
+    let buffer = fs::read("tests/DSC00512.jpg")?;
+    let mut iptc = IPTC::read_from_buffer(&buffer)?;
+
+    iptc.set_dataset(2, 230, b"desk-7");
+    iptc.add_dataset(2, 231, b"wire");
+    iptc.add_dataset(2, 231, b"web");
+    iptc.set_dataset(2, 90, b"Paris");
+    iptc.remove_dataset(2, 101);
+
+    assert_eq!(iptc.get(IPTCTag::City), "Paris");
+    assert_eq!(iptc.get(IPTCTag::CountryOrPrimaryLocationName), "");
+
+    let new_buffer = iptc.write_to_buffer(&buffer)?;
+    let new_iptc = IPTC::read_from_buffer(&new_buffer)?;
+
+    assert_eq!(new_iptc.get_dataset(2, 230), [b"desk-7".to_vec()]);
+    assert_eq!(
+        new_iptc.get_dataset(2, 231),
+        [b"wire".to_vec(), b"web".to_vec()]
+    );
+    assert_eq!(new_iptc.get_dataset(2, 90), [b"Paris".to_vec()]);
+    assert!(new_iptc.get_dataset(2, 101).is_empty());
+    assert_eq!(new_iptc.get(IPTCTag::City), "Paris");
+
+    // New datasets go after the existing ones, in record:dataset order
+    let last: Vec<_> = new_iptc
+        .datasets()
+        .iter()
+        .rev()
+        .take(3)
+        .map(|d| d.dataset)
+        .collect();
+    assert_eq!(last, [231, 231, 230]);
+
+    Ok(())
+}
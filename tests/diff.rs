@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::{Change, Diff};
+
+fn published() -> Result<(IPTC, IPTC), Box<dyn Error>> {
+    let ingested = IPTC::read_from_path("tests/smiley.jpg".as_ref())?;
+
+    let mut published = IPTC::read_from_path("tests/smiley.jpg".as_ref())?;
+    published.set_tag(IPTCTag::City, "Oslo");
+    published.data.remove(&IPTCTag::Urgency);
+    published.data.insert(
+        IPTCTag::Keywords,
+        vec!["rust".to_string(), "Yet another keyword".to_string()],
+    );
+    Ok((ingested, published))
+}
+
+#[test]
+fn equality_ignores_where_values_were_read() -> Result<(), Box<dyn Error>> {
+    let buffer = fs::read("tests/smiley.jpg")?;
+    let iptc = IPTC::read_from_buffer(&buffer)?;
+
+    let mut padded = iptc.write_to_buffer(&buffer)?;
+    padded.splice(2..2, [0xFF, 0xFE, 0x00, 0x04, b'h', b'i']);
+    assert_eq!(IPTC::read_from_buffer(&padded)?, iptc);
+    assert!(iptc.diff(&IPTC::read_from_buffer(&padded)?).is_empty());
+
+    let mut changed = IPTC::read_from_buffer(&buffer)?;
+    changed.set_tag(IPTCTag::City, "Oslo");
+    assert_ne!(changed, iptc);
+
+    Ok(())
+}
+
+#[test]
+fn diff_added_removed_and_changed() -> Result<(), Box<dyn Error>> {
+    let (ingested, published) = published()?;
+    let diff = ingested.diff(&published);
+
+    assert_eq!(
+        diff,
+        Diff {
+            changes: vec![
+                Change::Removed {
+                    tag: IPTCTag::Urgency,
+                    values: vec!["very!".to_string()],
+                },
+                Change::Changed {
+                    tag: IPTCTag::Keywords,
+                    old: vec!["Yet another keyword".to_string()],
+                    new: vec!["rust".to_string(), "Yet another keyword".to_string()],
+                },
+                Change::Added {
+                    tag: IPTCTag::City,
+                    values: vec!["Oslo".to_string()],
+                },
+            ]
+        }
+    );
+    assert_eq!(diff.changes[1].added_values(), vec!["rust"]);
+    assert!(diff.changes[1].removed_values().is_empty());
+
+    assert_eq!(
+        diff.to_string(),
+        "- Urgency: very!\n\
+         ~ Keywords: Yet another keyword -> rust, Yet another keyword (added rust)\n\
+         + City: Oslo\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn diff_is_order_aware() {
+    let mut old = IPTC::new();
+    old.set_tag(IPTCTag::Keywords, "a");
+    old.set_tag(IPTCTag::Keywords, "b");
+    let mut new = IPTC::new();
+    new.set_tag(IPTCTag::Keywords, "b");
+    new.set_tag(IPTCTag::Keywords, "a");
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.changes.len(), 1);
+    assert!(diff.changes[0].is_reordered());
+    assert_eq!(diff.to_string(), "~ Keywords: a, b -> b, a (reordered)\n");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn diff_as_json() -> Result<(), Box<dyn Error>> {
+    let (ingested, published) = published()?;
+
+    assert_eq!(
+        ingested.diff(&published).to_json(),
+        serde_json::json!({
+            "added": { "City": ["Oslo"] },
+            "removed": { "Urgency": ["very!"] },
+            "changed": {
+                "Keywords": {
+                    "old": ["Yet another keyword"],
+                    "new": ["rust", "Yet another keyword"],
+                    "added": ["rust"],
+                    "removed": [],
+                    "reordered": false
+                }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn diff_datasets_no_tag_covers() -> Result<(), Box<dyn Error>> {
+    let read = || IPTC::read_from_path("tests/smiley.jpg".as_ref());
+    let iptc = read()?;
+    let mut other = read()?;
+    other.set_dataset(2, 240, b"desk-7");
+
+    assert_ne!(iptc, other);
+    let diff = iptc.diff(&other);
+    assert_eq!(
+        diff.changes,
+        [Change::Dataset {
+            record: 2,
+            dataset: 240,
+            old: vec![],
+            new: vec![b"desk-7".to_vec()],
+        }]
+    );
+    assert_eq!(diff.changes[0].tag(), IPTCTag::Null);
+    assert_eq!(diff.to_string(), "+ 2:240: desk-7\n");
+
+    let mut changed = read()?;
+    changed.set_dataset(2, 240, b"desk-8");
+    assert_eq!(
+        other.diff(&changed).to_string(),
+        "~ 2:240: desk-7 -> desk-8\n"
+    );
+    assert!(other.diff(&other).is_empty());
+
+    // An empty value is a value: filling it in is a change, not an addition
+    let mut empty = read()?;
+    empty.set_dataset(2, 240, b"");
+    assert_eq!(empty.diff(&changed).to_string(), "~ 2:240:  -> desk-8\n");
+    assert_eq!(changed.diff(&empty).to_string(), "~ 2:240: desk-8 -> \n");
+
+    Ok(())
+}
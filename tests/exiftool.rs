@@ -0,0 +1,96 @@
+#![cfg(feature = "json")]
+
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use serde_json::json;
+
+#[test]
+fn export_like_exiftool() -> Result<(), Box<dyn Error>> {
+    let iptc = IPTC::read_from_path("tests/smiley.jpg".as_ref())?;
+
+    // exiftool -j -G1 -IPTC:all tests/smiley.jpg
+    assert_eq!(
+        iptc.to_exiftool_json("tests/smiley.jpg"),
+        json!({
+            "SourceFile": "tests/smiley.jpg",
+            "IPTC:EnvelopeRecordVersion": 42,
+            "IPTC:TimeSent": "14:41:00-05:00",
+            "IPTC:Headline": "The headline I am",
+            "IPTC:Urgency": "very!",
+            "IPTC:Keywords": "Yet another keyword",
+            "IPTC:DateCreated": "2004:08:03",
+            "IPTC:RasterizedCaption": "(Binary data 8 bytes, use -b option to extract)"
+        })
+    );
+
+    let iptc = IPTC::read_from_path("tests/DSC00512.jpg".as_ref())?;
+    let json = iptc.to_exiftool_json("tests/DSC00512.jpg");
+    assert_eq!(json["IPTC:CodedCharacterSet"], "UTF8");
+    assert_eq!(
+        json["IPTC:Keywords"],
+        json!(["London", "England", "Street", "Night"])
+    );
+    assert_eq!(json["IPTC:TimeCreated"], "16:29:32+00:00");
+    assert_eq!(json["IPTC:Country-PrimaryLocationName"], "UK");
+
+    Ok(())
+}
+
+#[test]
+fn export_numbers_and_coded_values() {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::Urgency, "5");
+    iptc.set_tag(IPTCTag::RecordVersion, "4");
+    iptc.set_tag(IPTCTag::ImageOrientation, "L");
+    iptc.set_tag(IPTCTag::Keywords, "2019");
+    iptc.set_tag(IPTCTag::Keywords, "007");
+    iptc.set_dataset(2, 55, b"20190519");
+    iptc.add_dataset(2, 120, b"Caf\xe9");
+
+    let json = iptc.to_exiftool_json("a.jpg");
+    assert_eq!(json["IPTC:Urgency"], "5 (normal urgency)");
+    assert_eq!(json["IPTC:ApplicationRecordVersion"], 4);
+    assert_eq!(json["IPTC:ImageOrientation"], "Landscape");
+    assert_eq!(json["IPTC:Keywords"], json!([2019, "007"]));
+    assert_eq!(json["IPTC:Caption-Abstract"], "base64:Q2Fm6Q==");
+}
+
+#[test]
+fn import_round_trip() -> Result<(), Box<dyn Error>> {
+    let exiftool = json!([{
+        "SourceFile": "a.jpg",
+        "IPTC:ApplicationRecordVersion": 4,
+        "IPTC:Urgency": "5 (normal urgency)",
+        "IPTC:Keywords": ["rust", 2019],
+        "IPTC:By-line": "Alvin",
+        "IPTC:DateCreated": "2019:05:19",
+        "IPTC:TimeCreated": "16:29:32+00:00",
+        "IPTC:Caption-Abstract": "base64:Q2Fm6Q==",
+        "IPTC:Destination": "Nowhere",
+        "File:FileSize": "12 kB"
+    }]);
+
+    let iptc = IPTC::from_exiftool_json(&exiftool[0])?;
+    assert_eq!(iptc.get(IPTCTag::RecordVersion), "4");
+    assert_eq!(iptc.get(IPTCTag::Urgency), "5");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "rust, 2019");
+    assert_eq!(iptc.get(IPTCTag::ByLine), "Alvin");
+    assert_eq!(iptc.get(IPTCTag::DateCreated), "20190519");
+    assert_eq!(iptc.get(IPTCTag::TimeCreated), "162932+0000");
+    assert_eq!(iptc.get_dataset(2, 120), [b"Caf\xe9".to_vec()]);
+
+    let mut expected = exiftool[0].clone();
+    expected.as_object_mut().unwrap().remove("IPTC:Destination");
+    expected.as_object_mut().unwrap().remove("File:FileSize");
+    assert_eq!(iptc.to_exiftool_json("a.jpg"), expected);
+
+    // Survives writing to a file too
+    let buffer = fs::read("tests/smiley.jpg")?;
+    let written = IPTC::read_from_buffer(&iptc.write_to_buffer(&buffer)?)?;
+    assert_eq!(written.get_dataset(2, 120), [b"Caf\xe9".to_vec()]);
+
+    Ok(())
+}
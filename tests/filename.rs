@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::{Collision, FilenameFormatter, Rename};
+
+mod common;
+use common::scratch_dir;
+
+fn photo() -> IPTC {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::DateCreated, "20240519");
+    iptc.set_tag(IPTCTag::TimeCreated, "142501+0200");
+    iptc.set_tag(IPTCTag::City, "Oslo");
+    iptc.set_tag(IPTCTag::ByLine, "Jane Doe");
+    iptc
+}
+
+#[test]
+fn format_pattern() -> Result<(), Box<dyn Error>> {
+    let path = Path::new("in/DSC_0001.JPG");
+
+    let formatter = FilenameFormatter::new("{DateCreated}_{City}_{ByLine}_{seq}.jpg")?;
+    assert_eq!(
+        formatter.format(&photo(), path, 7)?,
+        "20240519_Oslo_Jane Doe_7.jpg"
+    );
+
+    let formatter = FilenameFormatter::new(
+        "{DateCreated|date:%Y/%m-%d}/{TimeCreated|date:%H%M}_{seq|pad:4}_{stem}.{ext}",
+    )?;
+    assert_eq!(
+        formatter.format(&photo(), path, 12)?,
+        "2024/05-19/1425_0012_DSC_0001.JPG"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sanitise_truncate_and_default() -> Result<(), Box<dyn Error>> {
+    let mut iptc = photo();
+    iptc.data
+        .insert(IPTCTag::Headline, vec!["../Fire: what now?".to_string()]);
+
+    let formatter = FilenameFormatter::new("{Headline|max:10}_{Country|default:Unknown}.jpg")?;
+    assert_eq!(
+        formatter.format(&iptc, Path::new("a.jpg"), 1)?,
+        "_Fire_ wha_Unknown.jpg"
+    );
+
+    // Cutting doesn't leave a dot or space at the end, which Windows drops
+    iptc.data
+        .insert(IPTCTag::Headline, vec!["Fire at St. Mary".to_string()]);
+    let formatter = FilenameFormatter::new("{Headline|max:11}/{Headline|max:8}.jpg")?;
+    assert_eq!(
+        formatter.format(&iptc, Path::new("a.jpg"), 1)?,
+        "Fire at St/Fire at.jpg"
+    );
+
+    let formatter = FilenameFormatter::new("{Country}.jpg")?;
+    assert!(formatter.format(&iptc, Path::new("a.jpg"), 1).is_err());
+    let formatter = FilenameFormatter::new("{City|date:%Y}.jpg")?;
+    assert!(formatter.format(&iptc, Path::new("a.jpg"), 1).is_err());
+
+    // Values that sanitise to nothing take the default, or are an error,
+    // rather than leaving an empty directory name
+    iptc.data.insert(IPTCTag::City, vec![" . ".to_string()]);
+    let formatter = FilenameFormatter::new("{City|default:Unknown}/{seq}.jpg")?;
+    assert_eq!(
+        formatter.format(&iptc, Path::new("a.jpg"), 1)?,
+        "Unknown/1.jpg"
+    );
+    iptc.data.insert(IPTCTag::City, vec!["..".to_string()]);
+    let formatter = FilenameFormatter::new("{City}/{seq}.jpg")?;
+    assert!(formatter.format(&iptc, Path::new("a.jpg"), 1).is_err());
+
+    // Patterns that leave the directory of the file
+    for pattern in ["/{seq}.jpg", "../{seq}.jpg", "a//{seq}.jpg"] {
+        let formatter = FilenameFormatter::new(pattern)?;
+        assert!(
+            formatter.format(&iptc, Path::new("a.jpg"), 1).is_err(),
+            "{pattern}"
+        );
+    }
+
+    assert!(FilenameFormatter::new("{NotATag}").is_err());
+    assert!(FilenameFormatter::new("{City|upper}").is_err());
+    assert!(FilenameFormatter::new("{City|max:many}").is_err());
+    assert!(FilenameFormatter::new("{City").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rename_detects_collisions() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("rename-collisions", &["a.jpg", "b.jpg"])?;
+    let paths = [dir.join("a.jpg"), dir.join("b.jpg")];
+    let formatter = FilenameFormatter::new("{DateCreated}_{Headline}.jpg")?;
+
+    // Both files have the same date and headline
+    assert!(formatter.rename(&paths, Collision::Fail).is_err());
+    assert!(paths.iter().all(|path| path.exists()));
+
+    let renames = formatter.rename(&paths, Collision::Suffix)?;
+    assert_eq!(
+        renames,
+        vec![
+            Rename {
+                from: dir.join("a.jpg"),
+                to: dir.join("20040803_The headline I am.jpg"),
+            },
+            Rename {
+                from: dir.join("b.jpg"),
+                to: dir.join("20040803_The headline I am_1.jpg"),
+            },
+        ]
+    );
+    assert!(renames.iter().all(|r| !r.from.exists() && r.to.exists()));
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn rename_into_directories() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("rename-directories", &["a.jpg", "taken.jpg"])?;
+    fs::create_dir_all(dir.join("2004"))?;
+    fs::copy("tests/smiley.jpg", dir.join("2004/a.jpg"))?;
+
+    let formatter = FilenameFormatter::new("{DateCreated|date:%Y}/{stem}.jpg")?;
+    let planned = formatter.plan_renames(&[dir.join("a.jpg")], Collision::Fail);
+    assert!(planned.is_err(), "2004/a.jpg already exists");
+
+    let formatter = FilenameFormatter::new("{DateCreated|date:%Y}/{seq}.jpg")?;
+    let renames = formatter.rename(&[dir.join("a.jpg")], Collision::Fail)?;
+    assert_eq!(renames[0].to, dir.join("2004/1.jpg"));
+    assert!(dir.join("2004/1.jpg").exists());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::error::Error;
+
+use iptc::{IPTC, ImageFormat, guess_format};
+
+#[test]
+fn guess_formats() -> Result<(), Box<dyn Error>> {
+    let images: &[(&[u8], ImageFormat)] = &[
+        (&std::fs::read("tests/smiley.jpg")?, ImageFormat::Jpeg),
+        (&std::fs::read("tests/DSC3003.tif")?, ImageFormat::Tiff),
+        (b"II*\0\x08\0\0\0", ImageFormat::Tiff),
+        (b"MM\0*\0\0\0\x08", ImageFormat::Tiff),
+        (b"II+\0\x08\0\0\0\x10\0\0\0\0\0\0\0", ImageFormat::Tiff),
+        (b"\x89PNG\r\n\x1A\n\0\0\0\x0DIHDR", ImageFormat::Png),
+        (b"RIFF\x24\0\0\0WEBPVP8 ", ImageFormat::WebP),
+        (b"8BPS\0\x01\0\0\0\0\0\0", ImageFormat::Psd),
+        (b"GIF89a\x01\0\x01\0", ImageFormat::Gif),
+        (b"%!PS-Adobe-3.0 EPSF-3.0\n", ImageFormat::Eps),
+        (b"\xC5\xD0\xD3\xC6\x1E\0\0\0", ImageFormat::Eps),
+        (b"\0\0\0\x18ftypheic\0\0\0\0mif1heic", ImageFormat::Heif),
+        (b"\0\0\0\x1CftypavifAAAAmif1miafMA1B", ImageFormat::Avif),
+        // AVIF as a compatible brand of a generic HEIF file
+        (b"\0\0\0\x18ftypmif1\0\0\0\0mif1avif", ImageFormat::Avif),
+    ];
+    for (buffer, format) in images {
+        assert_eq!(guess_format(buffer)?, *format, "{buffer:?}");
+    }
+
+    assert!(guess_format(b"").is_err());
+    assert!(guess_format(b"not an image").is_err());
+    // A box that isn't `ftyp`, and a brand that isn't an image
+    assert!(guess_format(b"\0\0\0\x18moovheic\0\0\0\0mif1heic").is_err());
+    assert!(guess_format(b"\0\0\0\x14ftypisom\0\0\0\0mp41").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn unsupported_formats() -> Result<(), Box<dyn Error>> {
+    // Known formats without IPTC support read as empty
+    let png = b"\x89PNG\r\n\x1A\n\0\0\0\x0DIHDR";
+    assert_eq!(IPTC::read_from_buffer(png)?, IPTC::new());
+    assert_eq!(
+        IPTC::read_from_reader(&mut std::io::Cursor::new(png))?,
+        IPTC::new()
+    );
+
+    let error = IPTC::new()
+        .write_to_buffer(b"GIF89a\x01\0\x01\0")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Writing IPTC data is only supported for JPEG files"
+    );
+    assert!(IPTC::read_from_buffer(b"not an image").is_err());
+
+    Ok(())
+}
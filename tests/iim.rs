@@ -0,0 +1,102 @@
+use std::error::Error;
+
+use iptc::iim::{
+    Dataset, IPTC_RESOURCE_ID, MAX_VALUE_LENGTH, Resource, decode_datasets, decode_resources,
+    encode_datasets, encode_resources,
+};
+use iptc::{IPTC, IPTCTag};
+
+#[test]
+fn round_trip() -> Result<(), Box<dyn Error>> {
+    let datasets = vec![
+        Dataset::new(2, 0, &[0, 4]),
+        Dataset::new(2, 25, b"night"),
+        Dataset::new(2, 90, "Tromsø".as_bytes()),
+        Dataset::new(2, 240, b"\x01\x02"),
+    ];
+    let iim = encode_datasets(&datasets)?;
+    let irb = encode_resources(&[
+        Resource {
+            id: 0x03ED,
+            name: Vec::new(),
+            data: b"resolution".to_vec(),
+        },
+        Resource {
+            id: IPTC_RESOURCE_ID,
+            name: Vec::new(),
+            data: iim.clone(),
+        },
+    ]);
+
+    let resources = decode_resources(&irb)?;
+    assert_eq!(resources.len(), 2);
+    let iptc = resources
+        .iter()
+        .find(|r| r.id == IPTC_RESOURCE_ID)
+        .ok_or("no IPTC block")?;
+    assert_eq!(iptc.data, iim);
+
+    let decoded = decode_datasets(&iptc.data);
+    let values: Vec<_> = decoded.iter().map(|d| (d.tag(), d.decoded())).collect();
+    assert_eq!(
+        values,
+        [
+            (Some(IPTCTag::RecordVersion), "4".to_string()),
+            (Some(IPTCTag::Keywords), "night".to_string()),
+            (Some(IPTCTag::City), "Tromsø".to_string()),
+            (None, "\u{1}\u{2}".to_string()),
+        ]
+    );
+    assert_eq!(decoded[1].offset, Some(7));
+    assert_eq!(encode_datasets(&decoded)?, iim);
+
+    Ok(())
+}
+
+#[test]
+fn truncated_input() {
+    let iim =
+        encode_datasets(&[Dataset::new(2, 25, b"night"), Dataset::new(2, 25, b"day")]).unwrap();
+
+    // A cut value or header drops the dataset instead of panicking
+    for end in 0..iim.len() {
+        let datasets = decode_datasets(&iim[..end]);
+        assert_eq!(datasets.len(), usize::from(end >= 10));
+    }
+    assert!(decode_resources(b"Photoshop").is_err());
+
+    // A resource block cut after its signature is an error, wherever the cut is
+    let irb = encode_resources(&[Resource {
+        id: IPTC_RESOURCE_ID,
+        name: b"IPTC".to_vec(),
+        data: iim,
+    }]);
+    assert!(decode_resources(b"Photoshop 3.0\08BIM").is_err());
+    for end in 18..irb.len() {
+        assert!(decode_resources(&irb[..end]).is_err(), "{end}");
+    }
+    assert_eq!(decode_resources(&irb).unwrap().len(), 1);
+
+    // A block size that doesn't fit in memory
+    let huge = b"Photoshop 3.0\08BIM\x04\x04\0\0\xFF\xFF\xFF\xFF";
+    assert!(decode_resources(huge).is_err());
+}
+
+#[test]
+fn values_too_long() -> Result<(), Box<dyn Error>> {
+    let longest = vec![0x42; MAX_VALUE_LENGTH];
+    let iim = encode_datasets(&[Dataset::new(2, 202, &longest)])?;
+    assert_eq!(decode_datasets(&iim)[0].value, longest);
+
+    // The length would wrap, or set the extended dataset bit
+    let preview = vec![0x42; 70_000];
+    assert!(encode_datasets(&[Dataset::new(2, 202, &preview)]).is_err());
+    assert!(encode_datasets(&[Dataset::new(2, 202, &preview[..MAX_VALUE_LENGTH + 1])]).is_err());
+
+    let mut iptc = IPTC::new();
+    iptc.set_dataset(2, 202, &preview);
+    let jpeg = std::fs::read("tests/smiley.jpg")?;
+    assert!(iptc.write_to_buffer(&jpeg).is_err());
+
+    Ok(())
+}
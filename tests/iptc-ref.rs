@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::IptcRef;
+
+#[test]
+fn borrowed_view_matches_owned_read() -> Result<(), Box<dyn Error>> {
+    for path in ["tests/smiley.jpg", "tests/DSC00512.jpg"] {
+        let buffer = fs::read(path)?;
+        let owned = IPTC::read_from_buffer(&buffer)?;
+        let borrowed = IptcRef::from_buffer(&buffer)?;
+
+        let datasets: Vec<_> = borrowed.datasets().collect();
+        let owned_datasets = owned.datasets();
+        assert_eq!(datasets.len(), owned_datasets.len());
+        for ((record, dataset, value), owned) in datasets.iter().zip(&owned_datasets) {
+            assert_eq!(
+                (*record, *dataset, *value),
+                (owned.record, owned.dataset, &owned.value[..])
+            );
+        }
+
+        for tag in IPTCTag::iter() {
+            assert_eq!(borrowed.get(tag), owned.get(tag), "{} in {}", tag, path);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn values_borrow_from_the_buffer() -> Result<(), Box<dyn Error>> {
+    let buffer = fs::read("tests/DSC00512.jpg")?;
+    let iptc = IptcRef::from_buffer(&buffer)?;
+
+    let city = iptc.get_raw(2, 90).next().unwrap();
+    assert_eq!(city, b"London");
+    let range = buffer.as_ptr_range();
+    assert!(range.contains(&city.as_ptr()));
+
+    assert_eq!(
+        iptc.get_values(IPTCTag::Keywords),
+        ["London", "England", "Street", "Night"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bare_iim_and_empty_images() -> Result<(), Box<dyn Error>> {
+    let iim = b"\x1C\x02\x19\x00\x04rust\x1C\x02\x19\x00\x04RUST\x1C\x02\x5A\x00\x04Oslo";
+    let iptc = IptcRef::from_iim(iim);
+    assert_eq!(iptc.get(IPTCTag::Keywords), "rust");
+    assert_eq!(iptc.get(IPTCTag::City), "Oslo");
+    assert_eq!(iptc.datasets().count(), 3);
+
+    // A value that runs past the end is dropped
+    assert_eq!(
+        IptcRef::from_iim(b"\x1C\x02\x5A\x00\x09Oslo")
+            .datasets()
+            .count(),
+        0
+    );
+
+    let bare = [0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9];
+    assert!(IptcRef::from_buffer(&bare)?.is_empty());
+    assert!(IptcRef::from_buffer(&fs::read("tests/DSC3003.tif")?).is_err());
+
+    Ok(())
+}
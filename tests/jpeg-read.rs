@@ -4,13 +4,16 @@ use std::path::Path;
 use iptc::IPTC;
 use iptc::IPTCTag;
 
+mod common;
+use common::jpeg_with;
+
 #[test]
 fn exiv2_iptc_example() -> Result<(), Box<dyn Error>> {
     // https://exiv2.org/examples.html
     // Example 3: iptcprint.cpp
 
     let image_path = Path::new("tests/smiley.jpg");
-    let iptc = IPTC::read_from_path(&image_path)?;
+    let iptc = IPTC::read_from_path(image_path)?;
 
     let tags = iptc.get_all();
     println!("IPTC: {:?}", tags);
@@ -36,7 +39,7 @@ fn exiv2_iptc_example() -> Result<(), Box<dyn Error>> {
 #[test]
 fn street_photo_example() -> Result<(), Box<dyn Error>> {
     let image_path = Path::new("tests/DSC00512.jpg");
-    let iptc = IPTC::read_from_path(&image_path)?;
+    let iptc = IPTC::read_from_path(image_path)?;
 
     let tags = iptc.get_all();
     println!("IPTC: {:?}", tags);
@@ -58,3 +61,80 @@ fn street_photo_example() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+/// Counts the bytes read through it.
+struct CountingReader<R> {
+    inner: R,
+    read: usize,
+}
+
+impl<R: std::io::Read> std::io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n;
+        Ok(n)
+    }
+}
+
+impl<R: std::io::Seek> std::io::Seek for CountingReader<R> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn reader_stops_at_image_data() -> Result<(), Box<dyn Error>> {
+    let buffer = std::fs::read("tests/DSC00512.jpg")?;
+    let mut reader = CountingReader {
+        inner: std::io::Cursor::new(&buffer),
+        read: 0,
+    };
+    let iptc = IPTC::read_from_reader(&mut reader)?;
+
+    assert_eq!(iptc, IPTC::read_from_buffer(&buffer)?);
+    assert_eq!(iptc.datasets(), IPTC::read_from_buffer(&buffer)?.datasets());
+    assert!(reader.read < buffer.len() / 10);
+
+    Ok(())
+}
+
+#[test]
+fn other_app13_segments_are_skipped() -> Result<(), Box<dyn Error>> {
+    let mut jpeg = jpeg_with(&[(2, 90, b"Oslo")], &[], None);
+    jpeg.splice(2..2, *b"\xFF\xED\x00\x10Not Photoshop\0");
+
+    let iptc = IPTC::read_from_buffer(&jpeg)?;
+    assert_eq!(iptc.get(IPTCTag::City), "Oslo");
+    assert_eq!(
+        iptc,
+        IPTC::read_from_reader(&mut std::io::Cursor::new(&jpeg))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn truncated_jpeg() -> Result<(), Box<dyn Error>> {
+    let jpeg = jpeg_with(&[(2, 90, b"Oslo")], &[], None);
+
+    // Reading an APP13 segment cut short doesn't panic
+    for end in 4..jpeg.len() {
+        let _ = IPTC::read_from_buffer(&jpeg[..end]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn repeated_values_are_dropped_ignoring_case() -> Result<(), Box<dyn Error>> {
+    let keywords = [
+        "Fjord", "fjord", "FJORD", "Ålesund", "ÅLESUND", "ΟΔΟΣ", "οδος",
+    ];
+    let datasets: Vec<(u8, u8, &[u8])> = keywords.iter().map(|k| (2, 25, k.as_bytes())).collect();
+    let jpeg = jpeg_with(&datasets, &[], None);
+
+    let iptc = IPTC::read_from_buffer(&jpeg)?;
+    assert_eq!(iptc.get(IPTCTag::Keywords), "Fjord, Ålesund, ΟΔΟΣ");
+
+    Ok(())
+}
@@ -5,6 +5,9 @@ use std::path::Path;
 use iptc::IPTC;
 use iptc::IPTCTag;
 
+mod common;
+use common::{find, jpeg_with, scratch_dir};
+
 #[test]
 fn test_write_iptc() -> Result<(), Box<dyn Error>> {
     // Create a copy of the test file so we don't modify the original
@@ -14,7 +17,7 @@ fn test_write_iptc() -> Result<(), Box<dyn Error>> {
     fs::copy(original_path, test_path)?;
 
     // Read the original IPTC data
-    let mut iptc = IPTC::read_from_path(&test_path)?;
+    let mut iptc = IPTC::read_from_path(test_path)?;
 
     // Modify some tags
     iptc.set_tag(IPTCTag::City, "Oslo");
@@ -26,13 +29,13 @@ fn test_write_iptc() -> Result<(), Box<dyn Error>> {
     }
 
     // Write the changes
-    iptc.write_to_file(&test_path)?;
+    iptc.write_to_file(test_path)?;
 
     // Make a copy for debugging
     fs::copy(test_path, debug_path)?;
 
     // Read back and verify
-    let new_iptc = IPTC::read_from_path(&test_path)?;
+    let new_iptc = IPTC::read_from_path(test_path)?;
     assert_eq!(new_iptc.get(IPTCTag::City), "Oslo");
 
     // Verify all keywords are present
@@ -51,3 +54,312 @@ fn test_write_iptc() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_unknown_datasets_survive_write() -> Result<(), Box<dyn Error>> {
+    let buffer = jpeg_with(
+        &[
+            (2, 25, b"news"),
+            (2, 90, b"London"),
+            (2, 240, b"desk-7"),
+            (2, 241, b""),
+            (2, 101, b"UK"),
+        ],
+        &[],
+        None,
+    );
+
+    let mut iptc = IPTC::read_from_buffer(&buffer)?;
+    iptc.data.insert(IPTCTag::City, vec!["Oslo".to_string()]);
+    iptc.set_tag(IPTCTag::Headline, "Fjord");
+    let new_buffer = iptc.write_to_buffer(&buffer)?;
+
+    let city = find(&new_buffer, b"\x1C\x02\x5A\x00\x04Oslo").ok_or("City not written")?;
+    let custom = find(&new_buffer, b"\x1C\x02\xF0\x00\x06desk-7").ok_or("2:240 was dropped")?;
+    let empty = find(&new_buffer, b"\x1C\x02\xF1\x00\x00").ok_or("2:241 was dropped")?;
+    let country = find(&new_buffer, b"\x1C\x02\x65\x00\x02UK").ok_or("Country was dropped")?;
+    let headline = find(&new_buffer, b"\x1C\x02\x69\x00\x05Fjord").ok_or("Headline missing")?;
+
+    // Unknown datasets stay where they were, new tags go in record:dataset order
+    assert!(city < custom && custom < empty && empty < country);
+    assert!(country < headline);
+
+    let new_iptc = IPTC::read_from_buffer(&new_buffer)?;
+    assert_eq!(new_iptc.get(IPTCTag::City), "Oslo");
+    assert_eq!(new_iptc.get(IPTCTag::Keywords), "news");
+
+    Ok(())
+}
+
+/// Returns the IIM payload of the first IPTC resource block, without padding.
+fn iim_block(buffer: &[u8]) -> Option<&[u8]> {
+    let start = find(buffer, b"8BIM\x04\x04\x00\x00")? + 8;
+    let size = u32::from_be_bytes(buffer[start..start + 4].try_into().ok()?) as usize;
+    let block = buffer.get(start + 4..start + 4 + size)?;
+    let end = block.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    Some(&block[..end])
+}
+
+#[test]
+fn test_unchanged_round_trip_is_lossless() -> Result<(), Box<dyn Error>> {
+    for path in ["tests/smiley.jpg", "tests/DSC00512.jpg"] {
+        let buffer = fs::read(path)?;
+        let iptc = IPTC::read_from_buffer(&buffer)?;
+        let new_buffer = iptc.write_to_buffer(&buffer)?;
+
+        assert_eq!(iim_block(&new_buffer), iim_block(&buffer), "{}", path);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_writing_no_datasets_removes_app13() -> Result<(), Box<dyn Error>> {
+    let buffer = fs::read("tests/smiley.jpg")?;
+    let new_buffer = IPTC::new().write_to_buffer(&buffer)?;
+
+    assert_eq!(find(&new_buffer, b"Photoshop 3.0"), None);
+    assert!(IPTC::read_from_buffer(&new_buffer)?.get_all().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_other_resources_survive_write() -> Result<(), Box<dyn Error>> {
+    let mut buffer = jpeg_with(&[(2, 90, b"Paris")], &[], None);
+    // Append a resolution info block to the IRB, and fix up the APP13 length
+    let resource = b"8BIM\x03\xED\x00\x00\x00\x00\x00\x02\xAB\xCD";
+    let app13_end = find(&buffer, b"\xFF\xDA").ok_or("No SOS")?;
+    buffer.splice(app13_end..app13_end, resource.iter().copied());
+    let length = u16::from_be_bytes([buffer[4], buffer[5]]) + resource.len() as u16;
+    buffer[4..6].copy_from_slice(&length.to_be_bytes());
+
+    let mut iptc = IPTC::read_from_buffer(&buffer)?;
+    iptc.set_tag(IPTCTag::Headline, "Fjord");
+    let new_buffer = iptc.write_to_buffer(&buffer)?;
+
+    assert!(find(&new_buffer, resource).is_some());
+    assert_eq!(
+        IPTC::read_from_buffer(&new_buffer)?.get(IPTCTag::Headline),
+        "Fjord"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_write_to_file_keeps_times_and_backup() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("atomic-write", &["photo.jpg"])?;
+    let path = dir.join("photo.jpg");
+    let original = fs::read(&path)?;
+
+    let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+    fs::File::options()
+        .write(true)
+        .open(&path)?
+        .set_modified(modified)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640))?;
+    }
+
+    let mut iptc = IPTC::read_from_path(&path)?;
+    iptc.set_tag(IPTCTag::City, "Oslo");
+    let options = iptc::WriteOptions {
+        backup: true,
+        ..Default::default()
+    };
+    iptc.write_to_file_with(&path, &options)?;
+
+    assert_eq!(IPTC::read_from_path(&path)?.get(IPTCTag::City), "Oslo");
+    assert_eq!(fs::read(dir.join("photo.jpg.bak"))?, original);
+    assert_eq!(fs::metadata(&path)?.modified()?, modified);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o640);
+    }
+    // Only the file and its backup, no temporary file left behind
+    assert_eq!(fs::read_dir(&dir)?.count(), 2);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_writes_to_one_file() -> Result<(), Box<dyn Error>> {
+    let dir = scratch_dir("concurrent-write", &["photo.jpg"])?;
+    let path = dir.join("photo.jpg");
+
+    let cities = ["Oslo", "Bergen", "Tromsø", "Stavanger", "Bodø", "Ålesund"];
+    std::thread::scope(|scope| {
+        for city in cities {
+            let path = &path;
+            scope.spawn(move || {
+                let mut iptc = IPTC::read_from_path(path).unwrap();
+                iptc.data.insert(IPTCTag::City, vec![city.to_string()]);
+                iptc.write_to_file(path).unwrap();
+            });
+        }
+    });
+
+    // Every write replaced the file whole, and none left a temporary file
+    let city = IPTC::read_from_path(&path)?.get(IPTCTag::City);
+    assert!(cities.contains(&city.as_str()));
+    assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_in_place_reuses_padding() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join("iptc-in-place.jpg");
+    fs::copy("tests/smiley.jpg", &path)?;
+    let options = iptc::WriteOptions {
+        padding: 256,
+        ..Default::default()
+    };
+
+    // The original has no room to spare
+    let mut iptc = IPTC::read_from_path(&path)?;
+    iptc.set_tag(IPTCTag::City, "Oslo");
+    let method = iptc.write_to_file_in_place(&path, &options)?;
+    assert_eq!(method, iptc::WriteMethod::Rewrite);
+    let padded = fs::read(&path)?;
+
+    // Which it has now, so a new keyword goes into the padding
+    iptc.set_tag(IPTCTag::Keywords, "fjord");
+    let method = iptc.write_to_file_in_place(&path, &options)?;
+    assert_eq!(method, iptc::WriteMethod::InPlace);
+    let updated = fs::read(&path)?;
+    assert_eq!(updated.len(), padded.len());
+    let start = find(&padded, b"Photoshop 3.0").unwrap();
+    let end = start + u16::from_be_bytes([padded[start - 2], padded[start - 1]]) as usize - 2;
+    assert_eq!(updated[..start], padded[..start]);
+    assert_eq!(updated[end..], padded[end..]);
+
+    let new_iptc = IPTC::read_from_path(&path)?;
+    assert_eq!(new_iptc.get(IPTCTag::City), "Oslo");
+    assert!(new_iptc.get(IPTCTag::Keywords).contains("fjord"));
+
+    // More than the padding holds falls back to a rewrite
+    iptc.set_tag(IPTCTag::Caption, &"a".repeat(500));
+    let method = iptc.write_to_file_in_place(&path, &options)?;
+    assert_eq!(method, iptc::WriteMethod::Rewrite);
+    assert_eq!(
+        IPTC::read_from_path(&path)?.get(IPTCTag::Caption).len(),
+        500
+    );
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_to_writer_matches_buffer() -> Result<(), Box<dyn Error>> {
+    let mut iptc = IPTC::read_from_path(Path::new("tests/DSC00512.jpg"))?;
+    iptc.data.insert(IPTCTag::City, vec!["Oslo".to_string()]);
+    iptc.set_tag(IPTCTag::Keywords, "fjord");
+    let buffer = fs::read("tests/DSC00512.jpg")?;
+
+    let mut streamed = Vec::new();
+    iptc.write_to_writer(fs::File::open("tests/DSC00512.jpg")?, &mut streamed)?;
+    assert_eq!(streamed, iptc.write_to_buffer(&buffer)?);
+
+    // A JPEG without an APP13 segment gets one before the image data
+    let bare = jpeg_with(&[], &[], None);
+    let bare = [&bare[..2], &bare[bare.len() - 22..]].concat();
+    let mut streamed = Vec::new();
+    iptc.write_to_writer(&bare[..], &mut streamed)?;
+    assert_eq!(streamed, iptc.write_to_buffer(&bare)?);
+    assert_eq!(
+        IPTC::read_from_buffer(&streamed)?.get(IPTCTag::City),
+        "Oslo"
+    );
+
+    Ok(())
+}
+
+// DSC00512.jpg with 0xFF fill bytes before its first marker and before its
+// APP13 marker
+fn with_fill_bytes() -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut jpeg = fs::read("tests/DSC00512.jpg")?;
+    let app13 = jpeg
+        .windows(2)
+        .position(|w| w == [0xFF, 0xED])
+        .ok_or("no APP13 segment")?;
+    jpeg.insert(app13, 0xFF);
+    jpeg.splice(2..2, [0xFF, 0xFF]);
+    Ok(jpeg)
+}
+
+#[test]
+fn test_fill_bytes_before_markers() -> Result<(), Box<dyn Error>> {
+    let jpeg = with_fill_bytes()?;
+    let expected = IPTC::read_from_path(Path::new("tests/DSC00512.jpg"))?;
+
+    let iptc = IPTC::read_from_buffer(&jpeg)?;
+    assert_eq!(iptc, expected);
+    let streamed = IPTC::read_from_reader(&mut std::io::Cursor::new(&jpeg))?;
+    assert_eq!(streamed.datasets(), iptc.datasets());
+
+    let mut changed = IPTC::read_from_buffer(&jpeg)?;
+    changed.data.insert(IPTCTag::City, vec!["Oslo".to_string()]);
+    let mut written = Vec::new();
+    changed.write_to_writer(&jpeg[..], &mut written)?;
+    assert_eq!(written, changed.write_to_buffer(&jpeg)?);
+    assert_eq!(IPTC::read_from_buffer(&written)?.get(IPTCTag::City), "Oslo");
+
+    Ok(())
+}
+
+#[test]
+fn test_unchanged_tags_keep_their_bytes() -> Result<(), Box<dyn Error>> {
+    // Two Latin-1 keywords, which both decode to "", and two that only
+    // differ in case
+    let buffer = jpeg_with(
+        &[
+            (2, 25, b"\xe9t\xe9"),
+            (2, 25, b"caf\xe9"),
+            (2, 25, b"Cat"),
+            (2, 25, b"cat"),
+            (2, 90, b"Paris"),
+        ],
+        &[],
+        None,
+    );
+
+    let iptc = IPTC::read_from_buffer(&buffer)?;
+    assert_eq!(iptc.write_to_buffer(&buffer)?, buffer);
+
+    // Changing another tag leaves the keywords alone
+    let mut iptc = IPTC::read_from_buffer(&buffer)?;
+    iptc.data.insert(IPTCTag::City, vec!["Lyon".to_string()]);
+    let new_buffer = iptc.write_to_buffer(&buffer)?;
+    let keywords: Vec<Vec<u8>> = IPTC::read_from_buffer(&new_buffer)?.get_dataset(2, 25);
+    assert_eq!(keywords, [&b"\xe9t\xe9"[..], b"caf\xe9", b"Cat", b"cat"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_streams_cut_short() -> Result<(), Box<dyn Error>> {
+    let jpeg = jpeg_with(&[(2, 90, b"Oslo")], &[], None);
+    let image_data = find(&jpeg, b"\xFF\xDA").ok_or("No SOS")?;
+    let iptc = IPTC::read_from_buffer(&jpeg)?;
+
+    // Cut in a segment's marker or length, a stream still makes a header;
+    // cut in its payload, it is an error
+    for end in 2..=image_data {
+        let cut = &jpeg[..end];
+        let mut written = Vec::new();
+        let result = iptc.write_to_writer(cut, &mut written);
+        let in_header = end < 6 || end == image_data;
+        assert_eq!(result.is_ok(), in_header, "cut at {}", end);
+        let _ = IPTC::read_from_reader(&mut std::io::Cursor::new(cut));
+    }
+
+    Ok(())
+}
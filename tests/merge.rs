@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::MergeStrategy;
+
+fn agency() -> IPTC {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::Headline, "Agency headline");
+    iptc.set_tag(IPTCTag::Credit, "Agency");
+    iptc.set_tag(IPTCTag::Keywords, "London");
+    iptc.set_tag(IPTCTag::Keywords, "night");
+    iptc
+}
+
+fn editorial() -> IPTC {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::Headline, "Our headline");
+    iptc.set_tag(IPTCTag::Keywords, "Night");
+    iptc.set_tag(IPTCTag::Keywords, "street");
+    iptc
+}
+
+#[test]
+fn keep_existing() {
+    let mut iptc = editorial();
+    iptc.merge(&agency(), &MergeStrategy::KeepExisting);
+
+    assert_eq!(iptc.get(IPTCTag::Headline), "Our headline");
+    assert_eq!(iptc.get(IPTCTag::Credit), "Agency");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "Night, street");
+}
+
+#[test]
+fn overwrite() {
+    let mut iptc = editorial();
+    iptc.merge(&agency(), &MergeStrategy::Overwrite);
+
+    assert_eq!(iptc.get(IPTCTag::Headline), "Agency headline");
+    assert_eq!(iptc.get(IPTCTag::Credit), "Agency");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "London, night");
+}
+
+#[test]
+fn union_ignores_case() {
+    let mut iptc = editorial();
+    iptc.merge(&agency(), &MergeStrategy::Union);
+
+    assert_eq!(iptc.get(IPTCTag::Keywords), "Night, street, London");
+    // Non-repeatable tags can't be combined, so they are kept
+    assert_eq!(iptc.get(IPTCTag::Headline), "Our headline");
+    assert_eq!(iptc.get(IPTCTag::Credit), "Agency");
+}
+
+#[test]
+fn per_tag() {
+    let mut iptc = editorial();
+    iptc.set_tag(IPTCTag::Credit, "Us");
+    let strategy = MergeStrategy::PerTag(HashMap::from([
+        (IPTCTag::Headline, MergeStrategy::Overwrite),
+        (IPTCTag::Keywords, MergeStrategy::Union),
+    ]));
+    iptc.merge(&agency(), &strategy);
+
+    assert_eq!(iptc.get(IPTCTag::Headline), "Agency headline");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "Night, street, London");
+    assert_eq!(iptc.get(IPTCTag::Credit), "Us");
+}
+
+#[test]
+fn unknown_datasets_and_binary_values() -> Result<(), Box<dyn std::error::Error>> {
+    let source = IPTC::read_from_path("tests/smiley.jpg".as_ref())?;
+    let mut other = IPTC::new();
+    other.add_dataset(2, 240, b"desk-7");
+    other.merge(&source, &MergeStrategy::Union);
+    other.merge(&source, &MergeStrategy::Union);
+
+    assert_eq!(other.get_dataset(2, 125), source.get_dataset(2, 125));
+    assert_eq!(other.get_dataset(2, 240), vec![b"desk-7".to_vec()]);
+    assert_eq!(other.get(IPTCTag::Keywords), "Yet another keyword");
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::error::Error;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::{MetadataGroup, Redaction, RedactionPolicy, Selection, redact};
+
+mod common;
+use common::{XMP_SIGNATURE, find, jpeg_with};
+
+const XMP: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:ps="http://ns.adobe.com/photoshop/1.0/"
+    xmlns:xmpRights="http://ns.adobe.com/xap/1.0/rights/"
+    xmlns:xmpMM="http://ns.adobe.com/xap/1.0/mm/"
+    ps:Instructions="Call before use"
+    ps:City="Oslo"
+    xmpRights:Marked="True">
+   <dc:rights><rdf:Alt><rdf:li xml:lang="x-default">© Jane Doe</rdf:li></rdf:Alt></dc:rights>
+   <xmpMM:History><rdf:Seq><rdf:li>saved</rdf:li></rdf:Seq></xmpMM:History>
+   <dc:subject><rdf:Bag><rdf:li>night</rdf:li></rdf:Bag></dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+/// A JPEG with an XMP packet, and an APP13 segment with IIM datasets and
+/// resource blocks.
+fn jpeg() -> Vec<u8> {
+    let datasets: &[(u8, u8, &[u8])] = &[
+        (1, 90, b"\x1B%G"),
+        (2, 25, b"night"),
+        (2, 40, b"Call before use"),
+        (2, 80, b"Jane Doe"),
+        (2, 116, b"Jane Doe"),
+        (2, 118, b"+47 555 0100"),
+        (2, 240, b"desk-7"),
+    ];
+    let resources: &[(u16, &[u8])] = &[
+        (0x03ED, b"resolution"),
+        (0x0409, b"thumbnail!"),
+        (0x040A, b"\x01\x00"),
+    ];
+    jpeg_with(datasets, resources, Some(XMP.as_bytes()))
+}
+
+fn image_data(jpeg: &[u8]) -> &[u8] {
+    &jpeg[find(jpeg, &[0xFF, 0xDA]).unwrap()..]
+}
+
+#[test]
+fn remove_all() -> Result<(), Box<dyn Error>> {
+    let original = jpeg();
+    let (redacted, report) = redact(&original, &RedactionPolicy::RemoveAll)?;
+
+    assert_eq!(redacted, [&[0xFF, 0xD8], image_data(&original)].concat());
+    assert_eq!(IPTC::read_from_buffer(&redacted)?, IPTC::new());
+    // 7 datasets, 3 resource blocks and the XMP packet
+    assert_eq!(report.removed.len(), 11);
+    assert!(report.removed.contains(&Redaction::Resource {
+        id: 0x0409,
+        length: 10
+    }));
+    assert!(
+        report
+            .to_string()
+            .contains("- IIM 2:118 Contact: \"+47 555 0100\"\n")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn keep_rights_only() -> Result<(), Box<dyn Error>> {
+    let original = jpeg();
+    let (redacted, report) = redact(&original, &RedactionPolicy::KeepRightsOnly)?;
+    let iptc = IPTC::read_from_buffer(&redacted)?;
+
+    assert_eq!(image_data(&redacted), image_data(&original));
+    assert_eq!(iptc.get(IPTCTag::ByLine), "Jane Doe");
+    assert_eq!(iptc.get(IPTCTag::CopyrightNotice), "Jane Doe");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "");
+    assert_eq!(iptc.get_dataset(1, 90), vec![b"\x1B%G".to_vec()]);
+    assert!(iptc.get_dataset(2, 240).is_empty());
+
+    assert!(find(&redacted, b"resolution").is_none());
+    assert!(find(&redacted, b"thumbnail!").is_none());
+    assert!(find(&redacted, b"\x04\x0A").is_some());
+
+    assert!(find(&redacted, b"xmpRights:Marked=\"True\"").is_some());
+    assert!(find(&redacted, "© Jane Doe".as_bytes()).is_some());
+    assert!(find(&redacted, b"Call before use").is_none());
+    assert!(find(&redacted, b"night").is_none());
+    assert!(
+        report
+            .removed
+            .contains(&Redaction::XmpProperty("photoshop:City".into()))
+    );
+    assert!(
+        report
+            .removed
+            .contains(&Redaction::XmpProperty("dc:subject".into()))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn public() -> Result<(), Box<dyn Error>> {
+    let original = jpeg();
+    let (redacted, report) = redact(&original, &RedactionPolicy::Public)?;
+    let iptc = IPTC::read_from_buffer(&redacted)?;
+
+    assert_eq!(image_data(&redacted), image_data(&original));
+    assert_eq!(iptc.get(IPTCTag::Contact), "");
+    assert_eq!(iptc.get(IPTCTag::SpecialInstructions), "");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "night");
+    assert_eq!(iptc.get_dataset(2, 240), vec![b"desk-7".to_vec()]);
+    assert!(find(&redacted, b"resolution").is_some());
+
+    let removed: Vec<String> = report.removed.iter().map(|r| r.to_string()).collect();
+    assert_eq!(
+        removed,
+        [
+            "IIM 2:40 SpecialInstructions: \"Call before use\"",
+            "IIM 2:118 Contact: \"+47 555 0100\"",
+            "Resource 0x0409 (10 bytes)",
+            "XMP photoshop:Instructions",
+            "XMP xmpMM:History",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn allow_and_deny_lists() -> Result<(), Box<dyn Error>> {
+    let original = jpeg();
+
+    let allow = RedactionPolicy::Allow(vec![
+        Selection::Tag(IPTCTag::Keywords),
+        Selection::Group(MetadataGroup::Resources),
+    ]);
+    let (redacted, _) = redact(&original, &allow)?;
+    let iptc = IPTC::read_from_buffer(&redacted)?;
+    assert_eq!(iptc.get(IPTCTag::Keywords), "night");
+    assert_eq!(iptc.get(IPTCTag::ByLine), "");
+    assert!(find(&redacted, b"thumbnail!").is_some());
+    // Keywords are dc:subject in XMP
+    assert!(find(&redacted, b"<dc:subject>").is_some());
+    assert!(find(&redacted, b"dc:rights").is_none());
+
+    let deny = RedactionPolicy::Deny(vec!["Keywords".parse()?, "XMP".parse()?]);
+    let (redacted, report) = redact(&original, &deny)?;
+    let iptc = IPTC::read_from_buffer(&redacted)?;
+    assert_eq!(iptc.get(IPTCTag::Keywords), "");
+    assert_eq!(iptc.get(IPTCTag::Contact), "+47 555 0100");
+    assert!(find(&redacted, XMP_SIGNATURE).is_none());
+    assert_eq!(report.removed.len(), 2);
+
+    // Nothing to remove leaves the image as it was
+    let (unchanged, report) = redact(&original, &RedactionPolicy::Deny(Vec::new()))?;
+    assert_eq!(unchanged, original);
+    assert!(report.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "serde")]
+
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+
+#[test]
+fn serialize_to_json() -> Result<(), Box<dyn Error>> {
+    let iptc = IPTC::read_from_path("tests/DSC00512.jpg".as_ref())?;
+    let json = serde_json::to_value(&iptc)?;
+
+    assert_eq!(json["City"], "London");
+    assert_eq!(
+        json["Keywords"],
+        serde_json::json!(["London", "England", "Street", "Night"])
+    );
+    assert_eq!(json["DateCreated"], "20190519");
+
+    // Tags are written in record and dataset order
+    let text = serde_json::to_string(&iptc)?;
+    assert!(text.starts_with(r#"{"CodedCharacterSet":"#));
+    assert!(text.find("\"Keywords\"") < text.find("\"City\""));
+
+    assert_eq!(serde_json::to_string(&IPTCTag::ByLine)?, "\"ByLine\"");
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_and_write() -> Result<(), Box<dyn Error>> {
+    let json = r#"{
+        "City": "Oslo",
+        "Keywords": ["rust", "iptc"],
+        "IPTC:By-line": ["Alvin"],
+        "2:120": "A caption"
+    }"#;
+    let iptc: IPTC = serde_json::from_str(json)?;
+    assert_eq!(iptc.get(IPTCTag::ByLine), "Alvin");
+    assert_eq!(iptc.get(IPTCTag::Caption), "A caption");
+
+    let buffer = fs::read("tests/smiley.jpg")?;
+    let new_buffer = iptc.write_to_buffer(&buffer)?;
+    let new_iptc = IPTC::read_from_buffer(&new_buffer)?;
+    assert_eq!(new_iptc.get(IPTCTag::City), "Oslo");
+    assert_eq!(new_iptc.get(IPTCTag::Keywords), "rust, iptc");
+
+    // Round trip through JSON
+    let again: IPTC = serde_json::from_value(serde_json::to_value(&new_iptc)?)?;
+    assert_eq!(again.get_all(), new_iptc.get_all());
+
+    assert!(serde_json::from_str::<IPTC>(r#"{"Nope": "x"}"#).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::error::Error;
+
+use iptc::{IPTC, IPTCTag, TagInfo, ValueType};
+
+mod common;
+use common::jpeg_with;
+
+// The values read for `tag` from a JPEG holding `datasets`
+fn read_values(tag: IPTCTag, datasets: &[(u8, u8, &[u8])]) -> Result<Vec<String>, Box<dyn Error>> {
+    let iptc = IPTC::read_from_buffer(&jpeg_with(datasets, &[], None))?;
+    Ok(iptc.data.get(&tag).cloned().unwrap_or_default())
+}
+
+#[test]
+fn registry_metadata() {
+    let caption = IPTCTag::Caption.info().unwrap();
+    assert_eq!((caption.record, caption.dataset), (2, 120));
+    assert_eq!(caption.max_length, 2000);
+    assert_eq!(caption.iim_name, "Caption/Abstract");
+    assert_eq!(caption.exiftool_name, "Caption-Abstract");
+    assert_eq!(caption.xmp, Some("dc:description"));
+    assert!(!caption.repeatable);
+
+    let keywords = TagInfo::find(2, 25).unwrap();
+    assert_eq!(keywords.tag, IPTCTag::Keywords);
+    assert!(keywords.repeatable);
+    assert_eq!(keywords.max_length, 64);
+
+    let date_created = IPTCTag::DateCreated.info().unwrap();
+    assert_eq!(date_created.value_type, ValueType::Date);
+    assert!(IPTCTag::RecordVersion.info().unwrap().mandatory);
+
+    assert!(IPTCTag::Null.info().is_none());
+    assert!(TagInfo::find(2, 240).is_none());
+}
+
+// The entries below differ from the registry before IIM 4.2 was followed
+
+#[test]
+fn date_sent_is_1_70() -> Result<(), Box<dyn Error>> {
+    let info = IPTCTag::DateSent.info().unwrap();
+    assert_eq!((info.record, info.dataset), (1, 70));
+    assert_eq!(
+        TagInfo::find(1, 70).map(|info| info.tag),
+        Some(IPTCTag::DateSent)
+    );
+    assert!(TagInfo::find(1, 5).is_none());
+
+    let values = read_values(IPTCTag::DateSent, &[(1, 70, b"20240101")])?;
+    assert_eq!(values, vec!["20240101"]);
+    Ok(())
+}
+
+#[test]
+fn fixture_id_is_not_repeatable() -> Result<(), Box<dyn Error>> {
+    assert!(!IPTCTag::FixtureId.info().unwrap().repeatable);
+
+    let values = read_values(IPTCTag::FixtureId, &[(2, 22, b"first"), (2, 22, b"second")])?;
+    assert_eq!(values, vec!["second"]);
+    Ok(())
+}
+
+#[test]
+fn contact_is_repeatable() -> Result<(), Box<dyn Error>> {
+    assert!(IPTCTag::Contact.info().unwrap().repeatable);
+
+    let values = read_values(IPTCTag::Contact, &[(2, 118, b"desk"), (2, 118, b"editor")])?;
+    assert_eq!(values, vec!["desk", "editor"]);
+    Ok(())
+}
+
+#[test]
+fn object_attribute_reference_is_repeatable() -> Result<(), Box<dyn Error>> {
+    assert!(IPTCTag::ObjectAttributeReference.info().unwrap().repeatable);
+
+    let values = read_values(
+        IPTCTag::ObjectAttributeReference,
+        &[(2, 4, b"IPTC:001:Current"), (2, 4, b"IPTC:002:Analysis")],
+    )?;
+    assert_eq!(values, vec!["IPTC:001:Current", "IPTC:002:Analysis"]);
+    Ok(())
+}
+
+#[test]
+fn subject_reference_is_repeatable() -> Result<(), Box<dyn Error>> {
+    assert!(IPTCTag::SubjectReference.info().unwrap().repeatable);
+
+    let values = read_values(
+        IPTCTag::SubjectReference,
+        &[(2, 12, b"IPTC:15000000"), (2, 12, b"IPTC:04000000")],
+    )?;
+    assert_eq!(values, vec!["IPTC:15000000", "IPTC:04000000"]);
+    Ok(())
+}
+
+#[test]
+fn object_preview_file_format_is_short() -> Result<(), Box<dyn Error>> {
+    let info = IPTCTag::ObjectPreviewFileFormat.info().unwrap();
+    assert_eq!(info.value_type, ValueType::Short);
+
+    let values = read_values(IPTCTag::ObjectPreviewFileFormat, &[(2, 200, &[0x00, 0x0B])])?;
+    assert_eq!(values, vec!["11"]);
+    Ok(())
+}
+
+#[test]
+fn object_preview_file_format_version_is_short() -> Result<(), Box<dyn Error>> {
+    let info = IPTCTag::ObjectPreviewFileFormatVersion.info().unwrap();
+    assert_eq!(info.value_type, ValueType::Short);
+
+    let values = read_values(
+        IPTCTag::ObjectPreviewFileFormatVersion,
+        &[(2, 201, &[0x01, 0x02])],
+    )?;
+    assert_eq!(values, vec!["258"]);
+    Ok(())
+}
+
+#[test]
+fn registry_is_consistent() {
+    let mut numbers = HashSet::new();
+    for info in TagInfo::all() {
+        assert!(numbers.insert((info.record, info.dataset)));
+        assert_eq!(info.tag.info(), Some(info));
+        assert!(info.min_length <= info.max_length);
+    }
+
+    let mut sorted = numbers.into_iter().collect::<Vec<_>>();
+    sorted.sort();
+    let in_order: Vec<_> = TagInfo::all()
+        .iter()
+        .map(|info| (info.record, info.dataset))
+        .collect();
+    assert_eq!(in_order, sorted);
+}
+
+#[test]
+fn parse_tag_names() {
+    let cases = [
+        ("Keywords", IPTCTag::Keywords),
+        ("keywords", IPTCTag::Keywords),
+        ("IPTC:Caption-Abstract", IPTCTag::Caption),
+        ("Caption/Abstract", IPTCTag::Caption),
+        ("Description", IPTCTag::Caption),
+        ("2:120", IPTCTag::Caption),
+        ("By-line", IPTCTag::ByLine),
+        ("Creator", IPTCTag::ByLine),
+        ("iptc:By-lineTitle", IPTCTag::ByLineTitle),
+        ("Province-State", IPTCTag::ProvinceOrState),
+        (
+            "CountryOrPrimaryLocationName",
+            IPTCTag::CountryOrPrimaryLocationName,
+        ),
+        ("Job Identifier", IPTCTag::JobId),
+        ("1:90", IPTCTag::CodedCharacterSet),
+    ];
+    for (name, tag) in cases {
+        assert_eq!(name.parse::<IPTCTag>(), Ok(tag), "{}", name);
+    }
+
+    assert!("Null".parse::<IPTCTag>().is_err());
+    assert!("2:240".parse::<IPTCTag>().is_err());
+    assert_eq!(
+        "Nope".parse::<IPTCTag>().unwrap_err().to_string(),
+        "Unknown IPTC tag: Nope"
+    );
+}
+
+#[test]
+fn tag_from_numbers() {
+    assert_eq!(IPTCTag::try_from((2, 25)), Ok(IPTCTag::Keywords));
+    assert!(IPTCTag::try_from((2, 240)).is_err());
+}
+
+#[test]
+fn iterate_all_tags() {
+    let tags: Vec<IPTCTag> = IPTCTag::iter().collect();
+    assert_eq!(tags.len(), TagInfo::all().len());
+    assert_eq!(tags.first(), Some(&IPTCTag::ModelVersion));
+    assert!(!tags.contains(&IPTCTag::Null));
+
+    // Every tag parses back from its own name
+    for tag in tags {
+        assert_eq!(tag.to_string().parse::<IPTCTag>(), Ok(tag));
+    }
+}
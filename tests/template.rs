@@ -0,0 +1,123 @@
+#![cfg(feature = "template")]
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::{Template, TemplateContext, TemplateMode};
+
+const TOML: &str = r#"
+ByLine = "Jane Doe"
+Credit = { value = "Agency", mode = "overwrite" }
+CopyrightNotice = "© {year} Jane Doe"
+Keywords = { values = ["assignment", "{City}"], mode = "append" }
+ObjectName = "{filename} taken {DateCreated}"
+"#;
+
+fn context() -> TemplateContext {
+    TemplateContext {
+        year: 2024,
+        filename: Some("DSC_0001.jpg".to_string()),
+    }
+}
+
+#[test]
+fn toml_and_json_load_the_same() -> Result<(), Box<dyn Error>> {
+    let toml = Template::from_toml(TOML)?;
+    let json = Template::from_json(
+        r#"{
+            "Keywords": { "values": ["assignment", "{City}"], "mode": "append" },
+            "ObjectName": "{filename} taken {DateCreated}",
+            "ByLine": "Jane Doe",
+            "Credit": { "value": "Agency", "mode": "overwrite" },
+            "CopyrightNotice": ["© {year} Jane Doe"]
+        }"#,
+    )?;
+
+    assert_eq!(toml, json);
+    let credit = toml.fields.iter().find(|f| f.tag == IPTCTag::Credit);
+    assert_eq!(credit.map(|f| f.mode), Some(TemplateMode::Overwrite));
+
+    Ok(())
+}
+
+#[test]
+fn apply_with_modes_and_placeholders() -> Result<(), Box<dyn Error>> {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::ByLine, "John Smith");
+    iptc.set_tag(IPTCTag::Credit, "Someone else");
+    iptc.set_tag(IPTCTag::Keywords, "assignment");
+    iptc.set_tag(IPTCTag::City, "Oslo");
+    iptc.set_tag(IPTCTag::DateCreated, "20240519");
+
+    Template::from_toml(TOML)?.apply(&mut iptc, &context())?;
+
+    assert_eq!(iptc.get(IPTCTag::ByLine), "John Smith");
+    assert_eq!(iptc.get(IPTCTag::Credit), "Agency");
+    assert_eq!(iptc.get(IPTCTag::CopyrightNotice), "© 2024 Jane Doe");
+    assert_eq!(iptc.get(IPTCTag::Keywords), "assignment, Oslo");
+    assert_eq!(iptc.get(IPTCTag::ObjectName), "DSC_0001.jpg taken 20240519");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_templates() {
+    assert!(Template::from_toml("NotATag = \"x\"").is_err());
+    assert!(Template::from_toml("Headline = { value = \"x\", mode = \"append\" }").is_err());
+    assert!(Template::from_toml("Headline = { value = \"x\", mode = \"sometimes\" }").is_err());
+
+    let template = Template::from_toml("Headline = \"{nope}\"").unwrap();
+    assert!(template.apply(&mut IPTC::new(), &context()).is_err());
+
+    let template = Template::from_toml("Headline = \"{filename}\"").unwrap();
+    assert!(
+        template
+            .apply(&mut IPTC::new(), &TemplateContext::new(None))
+            .is_err()
+    );
+}
+
+#[test]
+fn apply_to_file() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join("iptc-template.jpg");
+    fs::copy("tests/smiley.jpg", &path)?;
+
+    let template_path = std::env::temp_dir().join("iptc-template.json");
+    fs::write(&template_path, r#"{ "ObjectName": "{filename}" }"#)?;
+    Template::from_path(&template_path)?.apply_to_file(&path)?;
+
+    let iptc = IPTC::read_from_path(&path)?;
+    assert_eq!(iptc.get(IPTCTag::ObjectName), "iptc-template.jpg");
+    assert!(Template::from_path(Path::new("template.yaml")).is_err());
+
+    fs::remove_file(&path)?;
+    fs::remove_file(&template_path)?;
+    Ok(())
+}
+
+#[test]
+fn context_uses_the_current_year() {
+    let year = TemplateContext::new(None).year;
+    assert!((2024..3000).contains(&year));
+}
+
+#[test]
+fn append_ignores_case() -> Result<(), Box<dyn Error>> {
+    let mut iptc = IPTC::new();
+    iptc.data.insert(
+        IPTCTag::Keywords,
+        vec!["Assignment".to_string(), "ÅLESUND".to_string()],
+    );
+
+    Template::from_toml(
+        r#"Keywords = { values = ["assignment", "Ålesund", "fjord"], mode = "append" }"#,
+    )?
+    .apply(&mut iptc, &context())?;
+
+    assert_eq!(iptc.get(IPTCTag::Keywords), "Assignment, ÅLESUND, fjord");
+
+    Ok(())
+}
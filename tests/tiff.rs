@@ -8,10 +8,21 @@ use iptc::IPTCTag;
 fn tiff_test() -> Result<(), Box<dyn Error>> {
     // Tiff files should work too
     let image_path = Path::new("tests/DSC3003.tif");
-    let iptc = IPTC::read_from_path(&image_path)?;
+    let iptc = IPTC::read_from_path(image_path)?;
 
     let province_or_state = iptc.get(IPTCTag::ProvinceOrState);
     assert_eq!(province_or_state, "Ontario");
 
     Ok(())
 }
+
+#[test]
+fn tiff_from_reader() -> Result<(), Box<dyn Error>> {
+    let buffer = std::fs::read("tests/DSC3003.tif")?;
+    let iptc = IPTC::read_from_reader(&mut std::io::Cursor::new(&buffer))?;
+
+    assert_eq!(iptc, IPTC::read_from_buffer(&buffer)?);
+    assert_eq!(iptc.get(IPTCTag::ProvinceOrState), "Ontario");
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::fs;
+
+use iptc::IPTC;
+use iptc::IPTCTag;
+use iptc::{MetadataGroup, Selection, TransferMode, TransferOptions, transfer};
+
+mod common;
+use common::{XMP_SIGNATURE, find, jpeg_with};
+
+#[test]
+fn copy_all_but_keywords() -> Result<(), Box<dyn Error>> {
+    let source = fs::read("tests/smiley.jpg")?;
+    let target = jpeg_with(&[(2, 25, b"keep"), (2, 90, b"Oslo")], &[], None);
+
+    let options = TransferOptions {
+        exclude: vec!["Keywords".parse()?],
+        ..Default::default()
+    };
+    let result = IPTC::read_from_buffer(&transfer(&source, &target, &options)?)?;
+
+    assert_eq!(result.get(IPTCTag::Headline), "The headline I am");
+    assert_eq!(result.get(IPTCTag::Keywords), "keep");
+    assert_eq!(result.get(IPTCTag::City), "Oslo");
+    // Binary values are copied byte for byte
+    assert_eq!(
+        result.get_dataset(2, 125),
+        IPTC::read_from_buffer(&source)?.get_dataset(2, 125)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn replace_selected_tags() -> Result<(), Box<dyn Error>> {
+    let source = jpeg_with(&[(2, 105, b"New headline")], &[], None);
+    let target = jpeg_with(
+        &[
+            (2, 90, b"Oslo"),
+            (2, 105, b"Old headline"),
+            (2, 120, b"Caption"),
+        ],
+        &[],
+        None,
+    );
+
+    let options = TransferOptions {
+        include: vec![
+            Selection::Tag(IPTCTag::Headline),
+            Selection::Tag(IPTCTag::Caption),
+        ],
+        mode: TransferMode::Replace,
+        ..Default::default()
+    };
+    let result = IPTC::read_from_buffer(&transfer(&source, &target, &options)?)?;
+
+    assert_eq!(result.get(IPTCTag::Headline), "New headline");
+    assert_eq!(result.get(IPTCTag::Caption), "");
+    assert_eq!(result.get(IPTCTag::City), "Oslo");
+
+    Ok(())
+}
+
+#[test]
+fn resources_and_xmp() -> Result<(), Box<dyn Error>> {
+    let source = jpeg_with(
+        &[(2, 90, b"Oslo")],
+        &[(0x03ED, b"source-resolution")],
+        Some(b"<x:xmpmeta>source</x:xmpmeta>"),
+    );
+    let target = jpeg_with(
+        &[],
+        &[(0x0425, b"target-digest")],
+        Some(b"<x:xmpmeta>target</x:xmpmeta>"),
+    );
+
+    let merged = transfer(&source, &target, &TransferOptions::default())?;
+    assert!(find(&merged, b"source-resolution").is_some());
+    assert!(find(&merged, b"target-digest").is_some());
+    assert!(find(&merged, b"source</x:xmpmeta>").is_some());
+    assert!(find(&merged, b"target</x:xmpmeta>").is_none());
+    assert_eq!(IPTC::read_from_buffer(&merged)?.get(IPTCTag::City), "Oslo");
+
+    let options = TransferOptions {
+        exclude: vec![Selection::Group(MetadataGroup::Xmp)],
+        mode: TransferMode::Replace,
+        ..Default::default()
+    };
+    let replaced = transfer(&source, &target, &options)?;
+    assert!(find(&replaced, b"source-resolution").is_some());
+    assert!(find(&replaced, b"target-digest").is_none());
+    assert!(find(&replaced, b"target</x:xmpmeta>").is_some());
+
+    Ok(())
+}
+
+#[test]
+fn xmp_from_tiff() -> Result<(), Box<dyn Error>> {
+    let source = fs::read("tests/DSC3003.tif")?;
+    let target = jpeg_with(&[], &[], None);
+
+    let options = TransferOptions {
+        include: vec!["XMP".parse()?],
+        ..Default::default()
+    };
+    let result = transfer(&source, &target, &options)?;
+
+    assert!(find(&result, XMP_SIGNATURE).is_some());
+    assert!(find(&result, b"Alvin Bryan").is_some());
+    assert_eq!(IPTC::read_from_buffer(&result)?.get(IPTCTag::ByLine), "");
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fs;
+
+use iptc::{IPTC, IPTCTag, ValidationError, ValueType, Violation, WriteOptions, WritePolicy};
+
+fn invalid_iptc() -> IPTC {
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::Caption, &"a".repeat(3000));
+    iptc.set_tag(IPTCTag::Keywords, "fine");
+    iptc.set_tag(IPTCTag::Keywords, &"k".repeat(100));
+    iptc.set_tag(IPTCTag::Urgency, "very!");
+    iptc.set_tag(IPTCTag::DateCreated, "2004-08-03");
+    iptc.set_tag(IPTCTag::TimeCreated, "144100-0500");
+    iptc.set_tag(IPTCTag::City, "Oslo");
+    iptc
+}
+
+#[test]
+fn validate_reports_every_violation() {
+    let violations = invalid_iptc().validate();
+
+    assert_eq!(
+        violations,
+        [
+            Violation::TooLong {
+                tag: IPTCTag::Urgency,
+                length: 5,
+                max_length: 1,
+            },
+            Violation::InvalidFormat {
+                tag: IPTCTag::Urgency,
+                value: "very!".to_string(),
+                expected: ValueType::Digits,
+            },
+            Violation::TooLong {
+                tag: IPTCTag::Keywords,
+                length: 100,
+                max_length: 64,
+            },
+            Violation::TooLong {
+                tag: IPTCTag::DateCreated,
+                length: 10,
+                max_length: 8,
+            },
+            Violation::InvalidFormat {
+                tag: IPTCTag::DateCreated,
+                value: "2004-08-03".to_string(),
+                expected: ValueType::Date,
+            },
+            Violation::TooLong {
+                tag: IPTCTag::Caption,
+                length: 3000,
+                max_length: 2000,
+            },
+        ]
+    );
+    assert_eq!(violations[0].tag(), IPTCTag::Urgency);
+
+    let mut iptc = IPTC::new();
+    iptc.set_tag(IPTCTag::DateCreated, "20041303");
+    iptc.set_tag(IPTCTag::City, "Oslo");
+    iptc.set_tag(IPTCTag::City, "Bergen");
+    assert_eq!(
+        iptc.validate()
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>(),
+        [
+            "DateCreated \"20041303\" should be a CCYYMMDD date",
+            "City is not repeatable but has 2 values",
+        ]
+    );
+}
+
+#[test]
+fn dates_with_unknown_month_or_day() {
+    for date in ["20250600", "20250000", "20250615"] {
+        let mut iptc = IPTC::new();
+        iptc.data
+            .insert(IPTCTag::DateCreated, vec![date.to_string()]);
+        assert_eq!(iptc.validate(), [], "{}", date);
+    }
+
+    let mut iptc = IPTC::new();
+    iptc.data
+        .insert(IPTCTag::DateCreated, vec!["20250632".to_string()]);
+    assert_eq!(iptc.validate().len(), 1);
+}
+
+#[test]
+fn write_policies() -> Result<(), Box<dyn Error>> {
+    let buffer = fs::read("tests/smiley.jpg")?;
+    let iptc = invalid_iptc();
+
+    let strict = WriteOptions {
+        policy: WritePolicy::Strict,
+        ..Default::default()
+    };
+    let error = iptc.write_to_buffer_with(&buffer, &strict).unwrap_err();
+    let error = error.downcast_ref::<ValidationError>().unwrap();
+    assert_eq!(error.violations.len(), 6);
+
+    let truncate = WriteOptions {
+        policy: WritePolicy::Truncate,
+        ..Default::default()
+    };
+    let new_buffer = iptc.write_to_buffer_with(&buffer, &truncate)?;
+    let new_iptc = IPTC::read_from_buffer(&new_buffer)?;
+    assert_eq!(new_iptc.get(IPTCTag::Caption).len(), 2000);
+    assert_eq!(new_iptc.data[&IPTCTag::Keywords][1].len(), 64);
+    assert_eq!(new_iptc.get(IPTCTag::Urgency), "v");
+    assert_eq!(new_iptc.get(IPTCTag::City), "Oslo");
+
+    let new_buffer = iptc.write_to_buffer(&buffer)?;
+    let new_iptc = IPTC::read_from_buffer(&new_buffer)?;
+    assert_eq!(new_iptc.get(IPTCTag::Caption).len(), 3000);
+
+    Ok(())
+}